@@ -0,0 +1,57 @@
+//! Integrates a state of charge over many steps, once with bare values and
+//! once with `TrackedState`s.
+//!
+//! Run with `RUSTFLAGS="--cfg mutation_tracing_untracked" cargo bench` to
+//! measure what the untracked build still costs over bare values: the step
+//! counters and the larger states, but no bookkeeping per access.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mutation_tracing::TrackedState;
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+const STEPS: usize = 10_000;
+
+fn bare(current: ElectricCurrent, voltage: ElectricPotential, dt: Time) -> Energy {
+    let mut energy = Energy::default();
+    for _ in 0..STEPS {
+        let pwr: Power = black_box(current) * voltage;
+        energy += pwr * dt;
+    }
+    energy
+}
+
+fn tracked(current: ElectricCurrent, voltage: ElectricPotential, dt: Time) -> Energy {
+    let mut pwr = TrackedState::<Power>::new("pwr");
+    let mut energy = TrackedState::<Energy>::new("energy");
+    energy.update(Energy::default());
+    for _ in 0..STEPS {
+        pwr.reset();
+        energy.reset();
+        pwr.update(black_box(current) * voltage);
+        energy.update(*energy.get_prev().unwrap() + *pwr.get().unwrap() * dt);
+        pwr.check();
+        energy.check();
+    }
+    *energy.get().unwrap()
+}
+
+fn integrate(c: &mut Criterion) {
+    let current = ElectricCurrent::new::<ampere>(2.0);
+    let voltage = ElectricPotential::new::<volt>(3.6);
+    let dt = Time::new::<second>(0.1);
+
+    let mut group = c.benchmark_group("integrate");
+    group.bench_function("bare", |b| b.iter(|| bare(current, voltage, dt)));
+    group.bench_function("tracked_state", |b| {
+        b.iter(|| tracked(current, voltage, dt))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, integrate);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+//! Derive macros for `mutation-tracing`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields, Index, Type, parse_macro_input};
+
+/// Derives `TrackedStates` for a struct.
+///
+/// Every field of type `TrackedState<_>` or `TrackedAccumulator<_>` is
+/// included automatically.  Fields holding nested components that also
+/// derive `TrackedStates` are included with `#[tracked_states(nested)]`, and
+/// tracked fields can be left out with `#[tracked_states(skip)]`.
+#[proc_macro_derive(TrackedStates, attributes(tracked_states))]
+pub fn derive_tracked_states(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`TrackedStates` can only be derived for structs",
+        ));
+    };
+
+    let mut members = Vec::new();
+    let mut names = Vec::new();
+    match &data.fields {
+        Fields::Named(fields) => {
+            for field in &fields.named {
+                if is_included(field)? {
+                    let ident = field.ident.clone().unwrap();
+                    names.push(ident.to_string());
+                    members.push(quote!(#ident));
+                }
+            }
+        }
+        Fields::Unnamed(fields) => {
+            for (i, field) in fields.unnamed.iter().enumerate() {
+                if is_included(field)? {
+                    let index = Index::from(i);
+                    names.push(i.to_string());
+                    members.push(quote!(#index));
+                }
+            }
+        }
+        Fields::Unit => {}
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::mutation_tracing::TrackedStates for #ident #ty_generics #where_clause {
+            fn reset_all(&mut self) {
+                #(::mutation_tracing::TrackedStates::reset_all(&mut self.#members);)*
+            }
+
+            fn collect_failures(
+                &self,
+                prefix: &str,
+                report: &mut ::mutation_tracing::CheckReport,
+            ) {
+                #(::mutation_tracing::TrackedStates::collect_failures(
+                    &self.#members,
+                    &::mutation_tracing::tracked_states::join_path(prefix, #names),
+                    report,
+                );)*
+            }
+
+            fn for_each<'__a>(
+                &'__a self,
+                prefix: &str,
+                f: &mut dyn FnMut(&str, &'__a dyn ::mutation_tracing::AnyTracked),
+            ) {
+                #(::mutation_tracing::TrackedStates::for_each(
+                    &self.#members,
+                    &::mutation_tracing::tracked_states::join_path(prefix, #names),
+                    f,
+                );)*
+            }
+
+            fn for_each_mut<'__a>(
+                &'__a mut self,
+                prefix: &str,
+                f: &mut dyn FnMut(&str, &'__a mut dyn ::mutation_tracing::AnyTracked),
+            ) {
+                #(::mutation_tracing::TrackedStates::for_each_mut(
+                    &mut self.#members,
+                    &::mutation_tracing::tracked_states::join_path(prefix, #names),
+                    f,
+                );)*
+            }
+
+            fn name_states(&mut self, prefix: &str) {
+                #(::mutation_tracing::TrackedStates::name_states(
+                    &mut self.#members,
+                    &::mutation_tracing::tracked_states::join_path(prefix, #names),
+                );)*
+            }
+        }
+    })
+}
+
+/// Whether a field takes part in the derived impl.
+fn is_included(field: &Field) -> syn::Result<bool> {
+    let mut nested = false;
+    let mut skip = false;
+    for attr in &field.attrs {
+        if !attr.path().is_ident("tracked_states") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("nested") {
+                nested = true;
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `nested` or `skip`"))
+            }
+        })?;
+    }
+    if nested && skip {
+        return Err(syn::Error::new_spanned(
+            field,
+            "a field cannot be both `nested` and `skip`",
+        ));
+    }
+    Ok(!skip && (nested || is_tracked(&field.ty)))
+}
+
+/// Whether the type's last path segment is `TrackedState` or
+/// `TrackedAccumulator`.
+fn is_tracked(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "TrackedState" || segment.ident == "TrackedAccumulator"
+        }),
+        _ => false,
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+use std::ops::{Add, Mul};
+use std::panic::Location;
+
+use crate::TrackedStateError;
+
+/// A running total that must receive exactly one increment per step, e.g.
+/// energy consumed or distance travelled.
+///
+/// The increment has the same type as the total, so with uom quantities it is
+/// checked at compile time to have the same dimension.  Unlike a
+/// [`TrackedState`](crate::TrackedState), the total is kept across resets.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedAccumulator<T: fmt::Debug> {
+    total: T,
+    increment: Option<T>,
+    name: String,
+    step: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    added_at: Option<&'static Location<'static>>,
+}
+
+/// A [`TrackedAccumulator`] without its name, see
+/// [`TrackedAccumulator::save`].
+#[derive(Clone, Debug)]
+pub(crate) struct SavedAccumulator<T> {
+    total: T,
+    increment: Option<T>,
+    step: usize,
+    added_at: Option<&'static Location<'static>>,
+}
+
+impl<T> TrackedAccumulator<T>
+where
+    T: fmt::Debug + Clone + Add<Output = T>,
+{
+    /// Creates an accumulator with the given name, starting from zero.
+    pub fn new(name: impl Into<String>) -> Self
+    where
+        T: Default,
+    {
+        Self::with_total(name, T::default())
+    }
+
+    /// Creates an accumulator with the given name and initial total.
+    pub fn with_total(name: impl Into<String>, total: T) -> Self {
+        Self {
+            total,
+            increment: None,
+            name: name.into(),
+            step: 0,
+            added_at: None,
+        }
+    }
+
+    /// Name of the accumulator, empty if none was given.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Renames the accumulator.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    /// Number of resets since the accumulator was created.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Total including the increment of the current step.
+    pub fn total(&self) -> &T {
+        &self.total
+    }
+
+    /// Increment added in the current step.
+    pub fn increment(&self) -> Option<&T> {
+        self.increment.as_ref()
+    }
+
+    /// Adds the increment for the current step.  Panics if one was already
+    /// added.
+    #[track_caller]
+    pub fn add(&mut self, increment: T) {
+        if let Err(err) = self.try_add(increment) {
+            panic!("{err}");
+        }
+    }
+
+    /// Adds the increment for the current step, failing if one was already
+    /// added.
+    #[track_caller]
+    pub fn try_add(&mut self, increment: T) -> Result<(), TrackedStateError> {
+        let caller = Location::caller();
+        if let Some(old) = &self.increment {
+            return Err(TrackedStateError::DoubleUpdate {
+                name: self.name.clone(),
+                step: self.step,
+                old: format!("{old:?}"),
+                new: format!("{increment:?}"),
+                first: self.added_at,
+                second: caller,
+            });
+        }
+        self.total = self.total.clone() + increment.clone();
+        self.increment = Some(increment);
+        self.added_at = Some(caller);
+        Ok(())
+    }
+
+    /// Adds the product of `a` and `b`, such as power times time step, as
+    /// the increment for the current step.
+    #[track_caller]
+    pub fn add_product<A, B>(&mut self, a: A, b: B)
+    where
+        A: Mul<B, Output = T>,
+    {
+        self.add(a * b);
+    }
+
+    /// Panics if no increment was added since the last reset.
+    pub fn check(&self) {
+        if let Err(err) = self.try_check() {
+            panic!("{err}");
+        }
+    }
+
+    /// Fails if no increment was added since the last reset.
+    pub fn try_check(&self) -> Result<(), TrackedStateError> {
+        match self.increment {
+            Some(_) => Ok(()),
+            None => Err(TrackedStateError::NotUpdated {
+                name: self.name.clone(),
+                step: self.step,
+            }),
+        }
+    }
+
+    /// Clears the increment so the next one can be added, keeping the total.
+    pub fn reset(&mut self) {
+        self.increment = None;
+        self.added_at = None;
+        self.step += 1;
+    }
+
+    /// Copies everything but the name, for a [`Snapshot`](crate::Snapshot).
+    pub(crate) fn save(&self) -> SavedAccumulator<T> {
+        SavedAccumulator {
+            total: self.total.clone(),
+            increment: self.increment.clone(),
+            step: self.step,
+            added_at: self.added_at,
+        }
+    }
+
+    /// Puts back what [`save`](Self::save) copied.
+    pub(crate) fn load(&mut self, saved: &SavedAccumulator<T>) {
+        self.total = saved.total.clone();
+        self.increment = saved.increment.clone();
+        self.step = saved.step;
+        self.added_at = saved.added_at;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrackedAccumulator;
+    use crate::TrackedStateError;
+
+    use uom::si::energy::joule;
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[test]
+    fn test_that_total_is_kept_across_resets() {
+        let mut energy = TrackedAccumulator::<Energy>::new("energy");
+
+        for pwr in [1.0, 2.0, 3.0] {
+            energy.add_product(Power::new::<watt>(pwr), Time::new::<second>(2.0));
+            energy.check();
+            energy.reset();
+        }
+
+        assert_eq!(*energy.total(), Energy::new::<joule>(12.0));
+        assert!(matches!(
+            energy.try_check(),
+            Err(TrackedStateError::NotUpdated { step: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_that_only_one_increment_per_step_is_allowed() {
+        let mut distance = TrackedAccumulator::with_total("distance", 10.0);
+
+        distance.add(1.0);
+        assert!(matches!(
+            distance.try_add(2.0),
+            Err(TrackedStateError::DoubleUpdate { .. })
+        ));
+        assert_eq!(*distance.total(), 11.0);
+        assert_eq!(distance.increment(), Some(&1.0));
+    }
+}
@@ -0,0 +1,411 @@
+use std::any::Any;
+use std::fmt;
+use std::ops::Add;
+
+use crate::accumulator::SavedAccumulator;
+use crate::tracked_state::SavedState;
+use crate::{
+    History, Snapshot, SnapshotError, StateSnapshot, TrackedAccumulator, TrackedState,
+    TrackedStateError, TrackedStates, value,
+};
+
+/// Object-safe view of a single [`TrackedState`] or [`TrackedAccumulator`],
+/// whatever its value type, so that states of different quantities can be
+/// kept in one collection.
+pub trait AnyTracked {
+    /// Name of the state.
+    fn name(&self) -> &str;
+
+    /// Renames the state.
+    fn set_name(&mut self, name: &str);
+
+    /// Number of resets since the state was created.
+    fn step(&self) -> usize;
+
+    /// Whether the state was updated in the current step.
+    fn is_updated(&self) -> bool;
+
+    /// Resets the state for the next step.
+    fn reset(&mut self);
+
+    /// Fails if the state does not pass its check.
+    fn try_check(&self) -> Result<(), TrackedStateError>;
+
+    /// Panics if the state does not pass its check.
+    fn check(&self) {
+        if let Err(err) = self.try_check() {
+            panic!("{err}");
+        }
+    }
+
+    /// `Debug` representation of the current value, if any.
+    fn debug_value(&self) -> Option<String>;
+
+    /// Current value as `f64` in SI base units, if any and of a
+    /// [registered](crate::value::register) type.
+    fn si_value(&self) -> Option<f64>;
+
+    /// `Debug` representation of the previous step's value, if any.
+    fn prev_debug_value(&self) -> Option<String> {
+        None
+    }
+
+    /// Previous step's value in SI base units, if any and of a registered
+    /// type.
+    fn prev_si_value(&self) -> Option<f64> {
+        None
+    }
+
+    /// Unit label of the state, see [`TrackedState::unit`].
+    fn unit(&self) -> Option<String>;
+
+    /// Whether values of the state have an SI representation, i.e. are of
+    /// a [registered](crate::value::register) type.
+    fn is_numeric(&self) -> bool {
+        false
+    }
+
+    /// Turns history recording on or off, dropping the recorded history
+    /// when turned off.  Does nothing for states without history.
+    fn set_recording(&mut self, _enabled: bool) {}
+
+    /// History in SI units, if recording is enabled.  Values of types that
+    /// are not registered are `None`.
+    fn si_history(&self) -> Option<History<f64>> {
+        None
+    }
+
+    /// Captures the full state, to be put back with
+    /// [`restore_state`](Self::restore_state).
+    fn save_state(&self) -> StateSnapshot;
+
+    /// Puts back the state captured by [`save_state`](Self::save_state),
+    /// keeping its name and settings.  Fails without changes if the snapshot
+    /// was taken from a state of another type.
+    fn restore_state(&mut self, snapshot: &StateSnapshot) -> Result<(), SnapshotError>;
+
+    /// Name of the concrete type, for diagnostics.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The state as [`Any`], for downcasting to its concrete type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Mutable version of [`as_any`](Self::as_any).
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T> AnyTracked for TrackedState<T>
+where
+    T: fmt::Debug + Clone + 'static,
+{
+    fn name(&self) -> &str {
+        TrackedState::name(self)
+    }
+
+    fn set_name(&mut self, name: &str) {
+        TrackedState::set_name(self, name);
+    }
+
+    fn step(&self) -> usize {
+        TrackedState::step(self)
+    }
+
+    fn is_updated(&self) -> bool {
+        self.current().is_some()
+    }
+
+    fn reset(&mut self) {
+        TrackedState::reset(self);
+    }
+
+    fn try_check(&self) -> Result<(), TrackedStateError> {
+        TrackedState::try_check(self)
+    }
+
+    fn debug_value(&self) -> Option<String> {
+        self.current().map(|v| format!("{v:?}"))
+    }
+
+    fn si_value(&self) -> Option<f64> {
+        self.current().and_then(value::to_si)
+    }
+
+    fn prev_debug_value(&self) -> Option<String> {
+        self.prev().map(|v| format!("{v:?}"))
+    }
+
+    fn prev_si_value(&self) -> Option<f64> {
+        self.prev().and_then(value::to_si)
+    }
+
+    fn unit(&self) -> Option<String> {
+        TrackedState::unit(self).map(str::to_string)
+    }
+
+    fn is_numeric(&self) -> bool {
+        value::si_converter::<T>().is_some()
+    }
+
+    fn set_recording(&mut self, enabled: bool) {
+        if enabled {
+            self.enable_history();
+        } else {
+            self.disable_history();
+        }
+    }
+
+    fn si_history(&self) -> Option<History<f64>> {
+        let convert = value::si_converter::<T>();
+        self.history()
+            .map(|history| history.map(|value| convert.as_ref().and_then(|convert| convert(value))))
+    }
+
+    fn save_state(&self) -> StateSnapshot {
+        StateSnapshot::new(self, self.save())
+    }
+
+    fn restore_state(&mut self, snapshot: &StateSnapshot) -> Result<(), SnapshotError> {
+        self.load(snapshot.data::<Self, SavedState<T>>()?);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl<T> AnyTracked for TrackedAccumulator<T>
+where
+    T: fmt::Debug + Clone + Add<Output = T> + 'static,
+{
+    fn name(&self) -> &str {
+        TrackedAccumulator::name(self)
+    }
+
+    fn set_name(&mut self, name: &str) {
+        TrackedAccumulator::set_name(self, name);
+    }
+
+    fn step(&self) -> usize {
+        TrackedAccumulator::step(self)
+    }
+
+    fn is_updated(&self) -> bool {
+        self.increment().is_some()
+    }
+
+    fn reset(&mut self) {
+        TrackedAccumulator::reset(self);
+    }
+
+    fn try_check(&self) -> Result<(), TrackedStateError> {
+        TrackedAccumulator::try_check(self)
+    }
+
+    /// The running total.
+    fn debug_value(&self) -> Option<String> {
+        Some(format!("{:?}", self.total()))
+    }
+
+    /// The running total.
+    fn si_value(&self) -> Option<f64> {
+        value::to_si(self.total())
+    }
+
+    fn unit(&self) -> Option<String> {
+        value::unit_of::<T>()
+    }
+
+    fn is_numeric(&self) -> bool {
+        value::si_converter::<T>().is_some()
+    }
+
+    fn save_state(&self) -> StateSnapshot {
+        StateSnapshot::new(self, self.save())
+    }
+
+    fn restore_state(&mut self, snapshot: &StateSnapshot) -> Result<(), SnapshotError> {
+        self.load(snapshot.data::<Self, SavedAccumulator<T>>()?);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Borrowed states of mixed value types, iterated in registration order.
+///
+/// States are addressed by their path when registered, which is their name
+/// or, for unnamed states of a collection, their path in it, see
+/// [`TrackedStates`].
+#[derive(Default)]
+pub struct StateRegistry<'a> {
+    states: Vec<(String, &'a mut dyn AnyTracked)>,
+}
+
+impl<'a> StateRegistry<'a> {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a single state, addressed by its name.
+    pub fn register(&mut self, state: &'a mut dyn AnyTracked) {
+        self.states.push((state.name().to_string(), state));
+    }
+
+    /// Adds every state of a collection, such as a model deriving
+    /// [`TrackedStates`], addressed by its path.
+    pub fn register_all(&mut self, states: &'a mut dyn TrackedStates) {
+        states.for_each_mut("", &mut |path, state| {
+            self.states.push((path.to_string(), state))
+        });
+    }
+
+    /// Number of registered states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether no states are registered.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The first state at `path`.
+    pub fn get(&self, path: &str) -> Option<&dyn AnyTracked> {
+        self.states
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, state)| &**state)
+    }
+
+    /// Mutable version of [`get`](Self::get).
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut dyn AnyTracked> {
+        self.states
+            .iter_mut()
+            .find(|(p, _)| p == path)
+            .map(|(_, state)| -> &mut dyn AnyTracked { &mut **state })
+    }
+
+    /// Paths of the registered states.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.states.iter().map(|(path, _)| path.as_str())
+    }
+
+    /// Iterates over the registered states.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn AnyTracked> {
+        self.states.iter().map(|(_, state)| &**state)
+    }
+
+    /// Captures every registered state, by path.  Fails if two states have
+    /// the same path.
+    pub fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+        Snapshot::capture(|f| {
+            self.states
+                .iter()
+                .for_each(|(path, state)| f(path, &**state))
+        })
+    }
+
+    /// Restores every registered state from the snapshot entry with its
+    /// path.  Fails without changes unless paths and types match exactly
+    /// and are unique.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let states = self
+            .states
+            .iter_mut()
+            .map(|(path, state)| (path.clone(), &mut **state as &mut dyn AnyTracked))
+            .collect();
+        snapshot.restore_into(states)
+    }
+
+    /// Iterates mutably over the registered states.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn AnyTracked> {
+        self.states
+            .iter_mut()
+            .map(|(_, state)| -> &mut dyn AnyTracked { &mut **state })
+    }
+}
+
+impl std::fmt::Debug for StateRegistry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.paths()).finish()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::StateRegistry;
+    use crate::{TrackedAccumulator, TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::kilowatt;
+    use uom::si::time::second;
+
+    #[test]
+    fn test_that_registry_holds_mixed_value_types() {
+        #[derive(Default, TrackedStates)]
+        struct Motor {
+            pwr: TrackedState<Power>,
+            energy: TrackedAccumulator<Energy>,
+        }
+
+        let mut motor = Motor::default();
+        motor.name_states("motor");
+        let mut dt = TrackedState::<Time>::new("dt");
+        let mut gear = TrackedState::<u8>::new("gear");
+
+        let mut registry = StateRegistry::new();
+        registry.register_all(&mut motor);
+        registry.register(&mut dt);
+        registry.register(&mut gear);
+        assert_eq!(registry.len(), 4);
+
+        registry
+            .get_mut("motor.pwr")
+            .unwrap()
+            .as_any_mut()
+            .downcast_mut::<TrackedState<Power>>()
+            .unwrap()
+            .update(Power::new::<kilowatt>(2.0));
+        registry
+            .get_mut("dt")
+            .unwrap()
+            .as_any_mut()
+            .downcast_mut::<TrackedState<Time>>()
+            .unwrap()
+            .update(Time::new::<second>(0.5));
+
+        let rows: Vec<_> = registry
+            .iter()
+            .map(|s| (s.name(), s.is_updated(), s.si_value(), s.unit()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("motor.pwr", true, Some(2000.0), Some("W".to_string())),
+                ("motor.energy", false, Some(0.0), Some("J".to_string())),
+                ("dt", true, Some(0.5), Some("s".to_string())),
+                ("gear", false, None, None),
+            ]
+        );
+        assert!(registry.get("gear").unwrap().try_check().is_err());
+
+        registry.iter_mut().for_each(|state| state.reset());
+        assert!(registry.iter().all(|state| state.step() == 1));
+        assert_eq!(motor.pwr.get_prev(), Some(&Power::new::<kilowatt>(2.0)));
+    }
+}
@@ -0,0 +1,350 @@
+//! Locating where two recorded runs of a model start to diverge.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::diff::{Sample, Series};
+use crate::{DependencyGraph, Diff, ToleranceError, TrackedStates};
+
+/// Finds the first divergence between two recorded runs and traces
+/// differences back through the [`DependencyGraph`] of a step.
+///
+/// The graph, recorded with [`dependency::record`](crate::dependency::record)
+/// around a single step, gives the update order and the inputs of each
+/// state.  Its nodes are the names of the states, which are their paths, so
+/// every state taking part must be named, e.g. with
+/// [`name_states`](crate::TrackedStates::name_states).  Only states recording
+/// history are compared.
+#[derive(Debug)]
+pub struct Bisection<'a> {
+    diff: &'a Diff,
+    graph: &'a DependencyGraph,
+    /// Baseline and current series of every state, in baseline order
+    /// followed by the states only the current run recorded.
+    states: Vec<(Option<Series>, Option<Series>)>,
+    /// Position of each path in `states`.
+    index: HashMap<String, usize>,
+}
+
+impl<'a> Bisection<'a> {
+    /// Compares the histories of `baseline` and `current` within the
+    /// tolerances of `diff`.  Fails if an absolute tolerance is applied to a
+    /// state in another unit.
+    pub fn new(
+        diff: &'a Diff,
+        graph: &'a DependencyGraph,
+        baseline: &dyn TrackedStates,
+        current: &dyn TrackedStates,
+    ) -> Result<Self, ToleranceError> {
+        let mut states: Vec<(Option<Series>, Option<Series>)> = Vec::new();
+        let mut index = HashMap::new();
+        for (series, is_baseline) in Series::histories(baseline)
+            .into_iter()
+            .map(|series| (series, true))
+            .chain(Series::histories(current).into_iter().map(|s| (s, false)))
+        {
+            diff.tolerance_for(&series)?;
+            let i = *index.entry(series.path.clone()).or_insert_with(|| {
+                states.push((None, None));
+                states.len() - 1
+            });
+            let (baseline, current) = &mut states[i];
+            let side = if is_baseline { baseline } else { current };
+            side.get_or_insert(series);
+        }
+        Ok(Self {
+            diff,
+            graph,
+            states,
+            index,
+        })
+    }
+
+    /// The earliest step in which any state differs beyond tolerance, and
+    /// among the states differing in it the first in update order, with
+    /// its inputs.  States missing from the graph come last, and states
+    /// recorded by only one run differ wherever the other has a value.
+    pub fn first_divergence(&self) -> Option<Divergence> {
+        let ((step, _, _), path) = self
+            .states
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (baseline, current))| {
+                let series = baseline.as_ref().or(current.as_ref())?;
+                let tolerance = self.diff.tolerance(&series.path);
+                let sides = [baseline, current].map(Option::as_ref);
+                let start = sides.iter().flatten().map(|s| s.start).min()?;
+                let end = sides
+                    .iter()
+                    .flatten()
+                    .map(|s| s.start + s.values.len())
+                    .max()?;
+                let step = (start..end).find(|&step| {
+                    let [baseline, current] =
+                        sides.map(|s| s.map_or(&Sample::Missing, |s| s.at(step)));
+                    tolerance.deviation(baseline, current).is_some()
+                })?;
+                let position = self.graph.position(&series.path).unwrap_or(usize::MAX);
+                Some(((step, position, i), series.path.as_str()))
+            })
+            .min_by_key(|&(key, _)| key)?;
+        self.divergence(path, step)
+    }
+
+    /// How the state at `path` differs in `step`, with its inputs, or
+    /// `None` if it is equal within tolerance.
+    pub fn divergence(&self, path: &str, step: usize) -> Option<Divergence> {
+        let (baseline, current) = self.samples(path, step)?;
+        let inputs = self
+            .graph
+            .inputs(path)
+            .unwrap_or_default()
+            .iter()
+            .filter(|input| step > 0 || !input.previous_step)
+            .map(|input| {
+                let step = step - usize::from(input.previous_step);
+                let (baseline, current, differs) = match self.samples(&input.name, step) {
+                    _ if self.series(&input.name).is_none() => {
+                        (Sample::Missing, Sample::Missing, None)
+                    }
+                    Some((baseline, current)) => (baseline, current, Some(true)),
+                    None => {
+                        let value = self.baseline_sample(&input.name, step);
+                        (value.clone(), value, Some(false))
+                    }
+                };
+                InputDivergence {
+                    path: input.name.clone(),
+                    step,
+                    previous_step: input.previous_step,
+                    baseline_value: baseline.to_string(),
+                    current_value: current.to_string(),
+                    differs,
+                }
+            })
+            .collect();
+        Some(Divergence {
+            path: path.to_string(),
+            unit: self.series(path).and_then(|s| s.unit.clone()),
+            step,
+            baseline_value: baseline.to_string(),
+            current_value: current.to_string(),
+            inputs,
+        })
+    }
+
+    /// Follows differing inputs backwards from the state at `path` in
+    /// `step`, always taking the first one read, until reaching a state
+    /// none of whose inputs is known to differ: the origin of the
+    /// difference, which is last in the returned chain.  Empty if the state does not differ.
+    pub fn trace_back(&self, path: &str, step: usize) -> Vec<Divergence> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut next = Some((path.to_string(), step));
+        while let Some((path, step)) = next.take() {
+            if !visited.insert((path.clone(), step)) {
+                break;
+            }
+            let Some(divergence) = self.divergence(&path, step) else {
+                break;
+            };
+            next = divergence
+                .inputs
+                .iter()
+                .find(|input| input.differs == Some(true))
+                .map(|input| (input.path.clone(), input.step));
+            chain.push(divergence);
+        }
+        chain
+    }
+
+    /// Baseline and current value of `path` in `step` if they differ.
+    fn samples(&self, path: &str, step: usize) -> Option<(Sample, Sample)> {
+        let (baseline, current) = &self.states[*self.index.get(path)?];
+        let [baseline, current] =
+            [baseline, current].map(|s| s.as_ref().map_or(Sample::Missing, |s| s.at(step).clone()));
+        self.diff
+            .tolerance(path)
+            .deviation(&baseline, &current)
+            .map(|_| (baseline, current))
+    }
+
+    /// The series of `path` in the baseline, or else in the current run.
+    fn series(&self, path: &str) -> Option<&Series> {
+        let (baseline, current) = &self.states[*self.index.get(path)?];
+        baseline.as_ref().or(current.as_ref())
+    }
+
+    /// Baseline value of `path` in `step`.
+    fn baseline_sample(&self, path: &str, step: usize) -> Sample {
+        self.index
+            .get(path)
+            .and_then(|&i| self.states[i].0.as_ref())
+            .map_or(Sample::Missing, |s| s.at(step).clone())
+    }
+}
+
+/// A state that differs beyond tolerance in a step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// Dotted path of the state.
+    pub path: String,
+    /// Unit of the state, see [`AnyTracked::unit`](crate::AnyTracked::unit).
+    pub unit: Option<String>,
+    /// Step in which it differs.
+    pub step: usize,
+    /// Baseline value, in SI units.
+    pub baseline_value: String,
+    /// Current value, in SI units.
+    pub current_value: String,
+    /// States read to compute it, in read order.
+    pub inputs: Vec<InputDivergence>,
+}
+
+/// An input of a [`Divergence`], as read by the baseline and current run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputDivergence {
+    /// Dotted path of the input.
+    pub path: String,
+    /// Step whose value was read.
+    pub step: usize,
+    /// Whether the previous step's value was read.
+    pub previous_step: bool,
+    /// Baseline value, in SI units.
+    pub baseline_value: String,
+    /// Current value, in SI units.
+    pub current_value: String,
+    /// Whether the values differ beyond tolerance, or `None` if neither run
+    /// recorded the input's history.
+    pub differs: Option<bool>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(unit) = self.unit.as_deref().filter(|unit| !unit.is_empty()) {
+            write!(f, " [{unit}]")?;
+        }
+        write!(
+            f,
+            " differs in step {}: {} instead of {}",
+            self.step, self.current_value, self.baseline_value
+        )?;
+        for input in &self.inputs {
+            write!(f, "\n  input {} in step {}", input.path, input.step)?;
+            if input.previous_step {
+                write!(f, " (prev)")?;
+            }
+            match input.differs {
+                Some(true) => write!(
+                    f,
+                    ": {} instead of {}",
+                    input.current_value, input.baseline_value
+                )?,
+                Some(false) => write!(f, ": equal ({})", input.baseline_value)?,
+                None => write!(f, ": not recorded")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::Bisection;
+    use crate::dependency::{self, DependencyGraph};
+    use crate::{Diff, TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[derive(Default, TrackedStates)]
+    struct Battery {
+        dt: TrackedState<Time>,
+        pwr: TrackedState<Power>,
+        energy: TrackedState<Energy>,
+        soc: TrackedState<f64>,
+    }
+
+    impl Battery {
+        fn step(&mut self, watts: f64) {
+            self.dt.update(Time::new::<second>(1.0));
+            self.pwr.update(Power::new::<watt>(watts));
+            self.energy
+                .update(*self.pwr.get().unwrap() * *self.dt.get().unwrap());
+            let soc = self.soc.get_prev().copied().unwrap_or(1.0);
+            self.soc
+                .update(soc - self.energy.get().unwrap().value / 100.0);
+        }
+    }
+
+    fn run(faulty_step: usize) -> (Battery, DependencyGraph) {
+        let mut battery = Battery::default();
+        battery.name_states("");
+        battery.set_recording_matching("**", true);
+        let mut graph = DependencyGraph::default();
+        for i in 0..5 {
+            let watts = if i == faulty_step { 2.0 } else { 1.0 };
+            graph = dependency::record(|| battery.step(watts)).1;
+            battery.reset_all();
+        }
+        (battery, graph)
+    }
+
+    #[test]
+    fn test_that_divergence_is_traced_back_to_its_origin() {
+        let (baseline, graph) = run(usize::MAX);
+        let (current, _) = run(2);
+        let diff = Diff::new();
+        let bisection = Bisection::new(&diff, &graph, &baseline, &current).unwrap();
+
+        let first = bisection.first_divergence().unwrap();
+        assert_eq!((first.path.as_str(), first.step), ("pwr", 2));
+        assert!(first.inputs.is_empty());
+
+        let chain = bisection.trace_back("soc", 4);
+        let steps: Vec<_> = chain.iter().map(|d| (d.path.as_str(), d.step)).collect();
+        assert_eq!(
+            steps,
+            [
+                ("soc", 4),
+                ("soc", 3),
+                ("soc", 2),
+                ("energy", 2),
+                ("pwr", 2)
+            ]
+        );
+        assert_eq!(
+            chain[3].to_string(),
+            "energy [J] differs in step 2: 2 instead of 1\n  \
+             input pwr in step 2: 2 instead of 1\n  \
+             input dt in step 2: equal (1)"
+        );
+        assert!(bisection.trace_back("dt", 4).is_empty());
+    }
+
+    #[test]
+    fn test_that_unrecorded_inputs_are_unknown_and_current_only_states_compared() {
+        let (mut baseline, graph) = run(usize::MAX);
+        let (mut current, _) = run(2);
+        baseline.set_recording_matching("dt", false);
+        current.set_recording_matching("dt", false);
+        let diff = Diff::new();
+
+        let bisection = Bisection::new(&diff, &graph, &baseline, &current).unwrap();
+        let energy = bisection.divergence("energy", 2).unwrap();
+        assert_eq!(energy.inputs[1].differs, None);
+        assert!(
+            energy
+                .to_string()
+                .ends_with("input dt in step 2: not recorded")
+        );
+
+        baseline.set_recording_matching("soc", false);
+        let bisection = Bisection::new(&diff, &graph, &baseline, &current).unwrap();
+        let first = bisection.first_divergence().unwrap();
+        assert_eq!((first.path.as_str(), first.step), ("soc", 0));
+    }
+}
@@ -0,0 +1,234 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+mod render;
+
+thread_local! {
+    static RECORDINGS: RefCell<Vec<Recording>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An active [`record`] call.
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+#[derive(Default)]
+struct Recording {
+    graph: DependencyGraph,
+    pending: Vec<Input>,
+}
+
+/// Runs `f` while recording which [`TrackedState`](crate::TrackedState)s are
+/// read before each update, returning the resulting dependency graph.
+///
+/// Every read since the previous update on this thread is attributed to the
+/// next update.  Recordings may be nested, in which case only the innermost
+/// one sees the reads and updates.
+pub fn record<R>(f: impl FnOnce() -> R) -> (R, DependencyGraph) {
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            RECORDINGS.with_borrow_mut(Vec::pop);
+        }
+    }
+
+    RECORDINGS.with_borrow_mut(|recordings| recordings.push(Recording::default()));
+    let _guard = Guard;
+    let result = f();
+    let graph = RECORDINGS
+        .with_borrow_mut(|recordings| std::mem::take(&mut recordings.last_mut().unwrap().graph));
+    (result, graph)
+}
+
+/// Notes a read of the state `name` in the innermost recording, if any.
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn on_read(name: &str, unit: Option<&str>, previous_step: bool, was_updated: bool) {
+    RECORDINGS.with_borrow_mut(|recordings| {
+        if let Some(recording) = recordings.last_mut() {
+            recording.pending.push(Input {
+                name: name.to_string(),
+                unit: unit.map(str::to_string),
+                previous_step,
+                was_updated,
+            });
+        }
+    });
+}
+
+/// Notes an update of the state `name` in the innermost recording, if any.
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn on_update(name: &str, unit: Option<&str>) {
+    RECORDINGS.with_borrow_mut(|recordings| {
+        if let Some(recording) = recordings.last_mut() {
+            let inputs = std::mem::take(&mut recording.pending);
+            recording.graph.nodes.push(UpdateNode {
+                name: name.to_string(),
+                unit: unit.map(str::to_string),
+                inputs,
+            });
+        }
+    });
+}
+
+/// A state read while computing an update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Input {
+    /// Name of the state that was read.
+    pub name: String,
+    /// Unit label of the state, see [`TrackedState::unit`](crate::TrackedState::unit).
+    pub unit: Option<String>,
+    /// Whether the previous step's value was read.
+    pub previous_step: bool,
+    /// Whether the state had already been updated when it was read.
+    pub was_updated: bool,
+}
+
+/// An update of a state together with the states read to compute it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateNode {
+    /// Name of the updated state.
+    pub name: String,
+    /// Unit label of the state, see [`TrackedState::unit`](crate::TrackedState::unit).
+    pub unit: Option<String>,
+    /// States read since the previous update, in read order.
+    pub inputs: Vec<Input>,
+}
+
+/// Updates recorded by [`record`], in update order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    nodes: Vec<UpdateNode>,
+}
+
+impl DependencyGraph {
+    /// All recorded updates in the order they happened.
+    pub fn nodes(&self) -> &[UpdateNode] {
+        &self.nodes
+    }
+
+    /// Names of the updated states in update order.
+    pub fn update_order(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().map(|node| node.name.as_str())
+    }
+
+    /// Position of the first update of `name`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    /// Inputs of the first update of `name`.
+    pub fn inputs(&self, name: &str) -> Option<&[Input]> {
+        self.position(name).map(|i| self.nodes[i].inputs.as_slice())
+    }
+
+    /// Reads of current-step values that happened before the value was
+    /// updated, as `(state, input)` pairs.
+    pub fn out_of_order(&self) -> Vec<(&str, &str)> {
+        self.nodes
+            .iter()
+            .flat_map(|node| {
+                node.inputs
+                    .iter()
+                    .filter(|input| !input.previous_step && !input.was_updated)
+                    .map(|input| (node.name.as_str(), input.name.as_str()))
+            })
+            .collect()
+    }
+
+    /// A cycle among current-step dependencies, if one exists, listed from a
+    /// state through its inputs back to itself.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in &self.nodes {
+            let targets = edges.entry(node.name.as_str()).or_default();
+            for input in node.inputs.iter().filter(|input| !input.previous_step) {
+                targets.push(input.name.as_str());
+            }
+        }
+
+        let mut done = HashSet::new();
+        for node in &self.nodes {
+            let mut path = Vec::new();
+            if let Some(cycle) = visit(node.name.as_str(), &edges, &mut path, &mut done) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+}
+
+/// Depth-first search for a cycle reachable from `name`.
+fn visit<'a>(
+    name: &'a str,
+    edges: &HashMap<&'a str, Vec<&'a str>>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+) -> Option<Vec<String>> {
+    if let Some(start) = path.iter().position(|&n| n == name) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+        cycle.push(name.to_string());
+        return Some(cycle);
+    }
+    if !done.insert(name) {
+        return None;
+    }
+    path.push(name);
+    for &input in edges.get(name).into_iter().flatten() {
+        if let Some(cycle) = visit(input, edges, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    None
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::record;
+    use crate::TrackedState;
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[test]
+    fn test_that_reads_are_attributed_to_the_next_update() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        let mut energy = TrackedState::<Energy>::new("energy");
+        let mut dt = TrackedState::<Time>::new("dt");
+
+        let ((), graph) = record(|| {
+            pwr.update(Power::new::<watt>(1.0));
+            dt.update(Time::new::<second>(1.0));
+            energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+        });
+
+        assert_eq!(
+            graph.update_order().collect::<Vec<_>>(),
+            ["pwr", "dt", "energy"]
+        );
+        assert!(graph.inputs("pwr").unwrap().is_empty());
+        let inputs: Vec<_> = graph
+            .inputs("energy")
+            .unwrap()
+            .iter()
+            .map(|input| input.name.as_str())
+            .collect();
+        assert_eq!(inputs, ["pwr", "dt"]);
+        assert!(graph.out_of_order().is_empty());
+        assert!(graph.find_cycle().is_none());
+    }
+
+    #[test]
+    fn test_that_out_of_order_reads_and_cycles_are_found() {
+        let mut a = TrackedState::<f64>::new("a");
+        let mut b = TrackedState::<f64>::new("b");
+
+        let ((), graph) = record(|| {
+            a.update(b.get().copied().unwrap_or_default() + 1.0);
+            b.update(a.get().unwrap() * 2.0);
+        });
+
+        assert_eq!(graph.out_of_order(), [("a", "b")]);
+        assert_eq!(graph.find_cycle().unwrap(), ["a", "b", "a"]);
+    }
+}
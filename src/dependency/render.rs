@@ -0,0 +1,192 @@
+use std::fmt::Write;
+
+use super::DependencyGraph;
+
+/// A node of a rendered graph.
+struct Node<'a> {
+    name: &'a str,
+    unit: Option<&'a str>,
+}
+
+impl Node<'_> {
+    /// Name followed by the unit in brackets, if it has one.
+    fn label(&self) -> String {
+        match self.unit {
+            Some(unit) if !unit.is_empty() => format!("{} [{unit}]", self.name),
+            _ => self.name.to_string(),
+        }
+    }
+}
+
+/// An edge from an input to the update that read it.
+struct Edge {
+    from: usize,
+    to: usize,
+    /// 1-based position of the update within the step.
+    order: usize,
+    previous_step: bool,
+}
+
+impl Edge {
+    fn label(&self) -> String {
+        if self.previous_step {
+            format!("{} (prev)", self.order)
+        } else {
+            self.order.to_string()
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// Renders the graph in Graphviz DOT format.
+    ///
+    /// Nodes are labeled with the state name and unit.  Edges point from
+    /// inputs to the states computed from them and are labeled with the
+    /// position of that update within the step; reads of the previous step's
+    /// value are dashed.
+    pub fn to_dot(&self) -> String {
+        let (nodes, edges) = self.layout();
+        let mut out = String::from("digraph states {\n");
+        for (i, node) in nodes.iter().enumerate() {
+            let label = node.label().replace('"', "\\\"");
+            writeln!(out, "    n{i} [label=\"{label}\"];").unwrap();
+        }
+        for edge in &edges {
+            let style = if edge.previous_step {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"{style}];",
+                edge.from,
+                edge.to,
+                edge.label()
+            )
+            .unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as a Mermaid flowchart, with the same labels as
+    /// [`to_dot`](Self::to_dot).
+    pub fn to_mermaid(&self) -> String {
+        let (nodes, edges) = self.layout();
+        let mut out = String::from("flowchart LR\n");
+        for (i, node) in nodes.iter().enumerate() {
+            let label = node.label().replace('"', "#quot;");
+            writeln!(out, "    n{i}[\"{label}\"]").unwrap();
+        }
+        for edge in &edges {
+            let arrow = if edge.previous_step { "-.->" } else { "-->" };
+            writeln!(
+                out,
+                "    n{} {arrow}|\"{}\"| n{}",
+                edge.from,
+                edge.label(),
+                edge.to
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// Nodes in update order, followed by inputs that were never updated,
+    /// and the edges between them.
+    fn layout(&self) -> (Vec<Node<'_>>, Vec<Edge>) {
+        let mut nodes = Vec::new();
+        for node in &self.nodes {
+            intern(&mut nodes, &node.name, node.unit.as_deref());
+        }
+
+        let mut edges = Vec::new();
+        for (order, node) in self.nodes.iter().enumerate() {
+            let to = intern(&mut nodes, &node.name, node.unit.as_deref());
+            for input in &node.inputs {
+                edges.push(Edge {
+                    from: intern(&mut nodes, &input.name, input.unit.as_deref()),
+                    to,
+                    order: order + 1,
+                    previous_step: input.previous_step,
+                });
+            }
+        }
+        (nodes, edges)
+    }
+}
+
+/// Index of the node named `name`, adding it if needed.
+fn intern<'a>(nodes: &mut Vec<Node<'a>>, name: &'a str, unit: Option<&'a str>) -> usize {
+    match nodes.iter().position(|node| node.name == name) {
+        Some(i) => i,
+        None => {
+            nodes.push(Node { name, unit });
+            nodes.len() - 1
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use crate::TrackedState;
+    use crate::dependency::record;
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    fn record_energy_step() -> crate::DependencyGraph {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        let mut energy = TrackedState::<Energy>::new("energy");
+        let mut dt = TrackedState::<Time>::new("dt");
+        let mut soc = TrackedState::<f64>::new("soc");
+        soc.update(1.0);
+        soc.reset();
+
+        record(|| {
+            pwr.update(Power::new::<watt>(1.0));
+            dt.update(Time::new::<second>(1.0));
+            energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+            soc.update(soc.get_prev().unwrap() - energy.get().unwrap().value);
+        })
+        .1
+    }
+
+    #[test]
+    fn test_that_dot_labels_units_and_update_order() {
+        assert_eq!(
+            record_energy_step().to_dot(),
+            "digraph states {
+    n0 [label=\"pwr [W]\"];
+    n1 [label=\"dt [s]\"];
+    n2 [label=\"energy [J]\"];
+    n3 [label=\"soc\"];
+    n0 -> n2 [label=\"3\"];
+    n1 -> n2 [label=\"3\"];
+    n3 -> n3 [label=\"4 (prev)\", style=dashed];
+    n2 -> n3 [label=\"4\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_that_mermaid_labels_units_and_update_order() {
+        assert_eq!(
+            record_energy_step().to_mermaid(),
+            "flowchart LR
+    n0[\"pwr [W]\"]
+    n1[\"dt [s]\"]
+    n2[\"energy [J]\"]
+    n3[\"soc\"]
+    n0 -->|\"3\"| n2
+    n1 -->|\"3\"| n2
+    n3 -.->|\"4 (prev)\"| n3
+    n2 -->|\"4\"| n3
+"
+        );
+    }
+}
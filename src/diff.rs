@@ -0,0 +1,576 @@
+//! Comparison of two [`Snapshot`]s or two sets of recorded histories, state
+//! by state, within float tolerances.
+
+use std::fmt;
+
+use crate::{History, Snapshot, StateSnapshot, ToleranceError, TrackedStates, TrackedValue, path};
+
+/// How far two values of a state may differ and still count as equal.
+///
+/// Two values `a` and `b` are equal if they are identical, both NaN, or if
+/// `|a - b|` is at most the absolute tolerance or the relative tolerance
+/// times the larger of `|a|` and `|b|`.  The default tolerance is zero, i.e.
+/// exact comparison.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tolerance {
+    abs: f64,
+    rel: f64,
+    unit: Option<String>,
+}
+
+impl Tolerance {
+    /// An absolute tolerance, e.g. `Power::new::<watt>(0.1)`, which may only
+    /// be applied to states with the same unit.  Fails if `abs` has no value
+    /// in SI units.
+    pub fn absolute<T>(abs: T) -> Result<Self, ToleranceError>
+    where
+        T: TrackedValue + fmt::Debug,
+    {
+        Self::default().with_absolute(abs)
+    }
+
+    /// A relative tolerance, e.g. `1e-9`.
+    pub fn relative(rel: f64) -> Self {
+        Self::default().with_relative(rel)
+    }
+
+    /// Sets the absolute tolerance.  Fails if `abs` has no value in SI
+    /// units.
+    pub fn with_absolute<T>(mut self, abs: T) -> Result<Self, ToleranceError>
+    where
+        T: TrackedValue + fmt::Debug,
+    {
+        self.abs = abs.to_si().ok_or_else(|| ToleranceError::NotNumeric {
+            value: format!("{abs:?}"),
+        })?;
+        self.unit = T::unit();
+        Ok(self)
+    }
+
+    /// Sets the relative tolerance.
+    pub fn with_relative(mut self, rel: f64) -> Self {
+        self.rel = rel;
+        self
+    }
+
+    /// Whether `a` and `b`, in SI units, are equal within the tolerance.
+    pub fn accepts(&self, a: f64, b: f64) -> bool {
+        if a == b || (a.is_nan() && b.is_nan()) {
+            return true;
+        }
+        let deviation = (a - b).abs();
+        deviation <= self.abs || deviation <= self.rel * a.abs().max(b.abs())
+    }
+
+    /// Absolute difference of `a` and `b` if they are not equal within the
+    /// tolerance; infinite if only one is a number or only one is NaN.
+    pub(crate) fn deviation(&self, a: &Sample, b: &Sample) -> Option<f64> {
+        match (a, b) {
+            (Sample::Number(x), Sample::Number(y)) if self.accepts(*x, *y) => None,
+            (Sample::Number(x), Sample::Number(y)) => {
+                let deviation = (x - y).abs();
+                Some(if deviation.is_nan() {
+                    f64::INFINITY
+                } else {
+                    deviation
+                })
+            }
+            (x, y) if x == y => None,
+            _ => Some(f64::INFINITY),
+        }
+    }
+}
+
+/// Compares states within per-state [`Tolerance`]s.
+///
+/// ```
+/// # use mutation_tracing::diff::{Diff, Tolerance};
+/// # use uom::si::{f64::Power, power::watt};
+/// let diff = Diff::new()
+///     .with_default(Tolerance::relative(1e-12))
+///     .with_tolerance("**.pwr", Tolerance::absolute(Power::new::<watt>(0.1))?);
+/// # Ok::<(), mutation_tracing::ToleranceError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Diff {
+    default: Tolerance,
+    tolerances: Vec<(String, Tolerance)>,
+}
+
+impl Diff {
+    /// Creates a diff comparing exactly.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tolerance for states not matched by any
+    /// [`with_tolerance`](Self::with_tolerance) pattern.
+    pub fn with_default(mut self, tolerance: Tolerance) -> Self {
+        self.default = tolerance;
+        self
+    }
+
+    /// Sets the tolerance for states whose path matches the glob `pattern`.
+    /// Later patterns take precedence.
+    pub fn with_tolerance(mut self, pattern: &str, tolerance: Tolerance) -> Self {
+        self.tolerances.push((pattern.to_string(), tolerance));
+        self
+    }
+
+    /// Tolerance for the state at `path`.
+    pub fn tolerance(&self, path: &str) -> &Tolerance {
+        self.tolerances
+            .iter()
+            .rev()
+            .find(|(pattern, _)| path::matches(pattern, path))
+            .map_or(&self.default, |(_, tolerance)| tolerance)
+    }
+
+    /// Compares two snapshots: the current and previous values and, for
+    /// states recording them, the captured histories.  States that are not
+    /// numeric are compared by their `Debug` representation.  Fails if an
+    /// absolute tolerance is applied to a state in another unit.
+    pub fn snapshots(
+        &self,
+        baseline: &Snapshot,
+        current: &Snapshot,
+    ) -> Result<DiffReport, ToleranceError> {
+        let series = |snapshot: &Snapshot| {
+            snapshot
+                .iter()
+                .map(|(path, state)| Series::snapshot(path, state))
+                .collect::<Vec<_>>()
+        };
+        self.series(&series(baseline), &series(current))
+    }
+
+    /// Compares the recorded histories of two models, step by step.  Only
+    /// states recording history are compared.  Fails if an absolute
+    /// tolerance is applied to a state in another unit.
+    pub fn histories(
+        &self,
+        baseline: &dyn TrackedStates,
+        current: &dyn TrackedStates,
+    ) -> Result<DiffReport, ToleranceError> {
+        self.series(&Series::histories(baseline), &Series::histories(current))
+    }
+
+    pub(crate) fn series(
+        &self,
+        baseline: &[Series],
+        current: &[Series],
+    ) -> Result<DiffReport, ToleranceError> {
+        let mut report = DiffReport::default();
+        for a in baseline {
+            match current.iter().find(|b| b.path == a.path) {
+                Some(b) => report.changed.extend(self.compare(a, b)?),
+                None => report.missing.push(a.path.clone()),
+            }
+        }
+        report.added = current
+            .iter()
+            .filter(|b| !baseline.iter().any(|a| a.path == b.path))
+            .map(|b| b.path.clone())
+            .collect();
+        Ok(report)
+    }
+
+    /// Tolerance for the state of `series`, checked to be in its unit.
+    pub(crate) fn tolerance_for(&self, series: &Series) -> Result<&Tolerance, ToleranceError> {
+        let tolerance = self.tolerance(&series.path);
+        if let Some(unit) = &tolerance.unit
+            && tolerance.abs != 0.0
+            && series.unit.as_deref().unwrap_or_default() != unit
+        {
+            return Err(ToleranceError::UnitMismatch {
+                path: series.path.clone(),
+                expected: unit.clone(),
+                found: series.unit.clone(),
+            });
+        }
+        Ok(tolerance)
+    }
+
+    fn compare(&self, a: &Series, b: &Series) -> Result<Option<StateDiff>, ToleranceError> {
+        let tolerance = self.tolerance_for(a)?;
+        let start = a.start.min(b.start);
+        let end = (a.start + a.values.len()).max(b.start + b.values.len());
+        let mut diff: Option<StateDiff> = None;
+        for step in start..end {
+            let Some(deviation) = tolerance.deviation(a.at(step), b.at(step)) else {
+                continue;
+            };
+            let diff = diff.get_or_insert_with(|| StateDiff {
+                path: a.path.clone(),
+                unit: a.unit.clone(),
+                first_step: step,
+                baseline_value: a.at(step).to_string(),
+                current_value: b.at(step).to_string(),
+                max_deviation: deviation,
+                max_step: step,
+            });
+            if deviation > diff.max_deviation {
+                diff.max_deviation = deviation;
+                diff.max_step = step;
+            }
+        }
+        Ok(diff)
+    }
+}
+
+/// A value of a state at one step.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Sample {
+    Number(f64),
+    Text(String),
+    Missing,
+}
+
+impl Sample {
+    fn new(si_value: Option<f64>, debug_value: Option<&str>) -> Self {
+        match (si_value, debug_value) {
+            (Some(value), _) => Self::Number(value),
+            (None, Some(text)) => Self::Text(text.to_string()),
+            (None, None) => Self::Missing,
+        }
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Text(text) => write!(f, "{text}"),
+            Self::Missing => write!(f, "no value"),
+        }
+    }
+}
+
+/// Values of a state over consecutive steps.
+#[derive(Clone, Debug)]
+pub(crate) struct Series {
+    pub(crate) path: String,
+    pub(crate) unit: Option<String>,
+    /// Step of the first value.
+    pub(crate) start: usize,
+    pub(crate) values: Vec<Sample>,
+}
+
+impl Series {
+    /// Histories in SI units of the states in `states` that record them.
+    pub(crate) fn histories(states: &dyn TrackedStates) -> Vec<Self> {
+        let mut series = Vec::new();
+        states.for_each("", &mut |path, state| {
+            if let Some(history) = state.si_history() {
+                series.push(Self::history(path, state.unit(), &history));
+            }
+        });
+        series
+    }
+
+    fn history(path: &str, unit: Option<String>, history: &History<f64>) -> Self {
+        Self {
+            path: path.to_string(),
+            unit,
+            start: history.start(),
+            values: history
+                .iter()
+                .skip(history.start())
+                .map(|v| v.map_or(Sample::Missing, |&v| Sample::Number(v)))
+                .collect(),
+        }
+    }
+
+    /// The captured history of `state` if any, followed by its previous and
+    /// current value.
+    fn snapshot(path: &str, state: &StateSnapshot) -> Self {
+        let unit = state.unit().map(str::to_string);
+        let step = state.step();
+        let mut series = match state.si_history() {
+            Some(history) => Self::history(path, unit, history),
+            None => Self {
+                path: path.to_string(),
+                unit,
+                start: step,
+                values: Vec::new(),
+            },
+        };
+        let first = step.saturating_sub(1);
+        if series.start > first {
+            let padding = series.start - first;
+            series
+                .values
+                .splice(0..0, std::iter::repeat_n(Sample::Missing, padding));
+            series.start = first;
+        }
+        series.values.resize(first - series.start, Sample::Missing);
+        if step > 0 {
+            series
+                .values
+                .push(Sample::new(state.prev_si_value(), state.prev_debug_value()));
+        }
+        series
+            .values
+            .push(Sample::new(state.si_value(), state.debug_value()));
+        series
+    }
+
+    /// Value at `step`, missing outside the recorded range.
+    pub(crate) fn at(&self, step: usize) -> &Sample {
+        step.checked_sub(self.start)
+            .and_then(|i| self.values.get(i))
+            .unwrap_or(&Sample::Missing)
+    }
+}
+
+/// A state whose values differ beyond its tolerance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateDiff {
+    /// Dotted path of the state.
+    pub path: String,
+    /// Unit of the state, see [`AnyTracked::unit`](crate::AnyTracked::unit).
+    pub unit: Option<String>,
+    /// First step in which the values differ beyond the tolerance.
+    pub first_step: usize,
+    /// Baseline value at `first_step`, in SI units.
+    pub baseline_value: String,
+    /// Current value at `first_step`, in SI units.
+    pub current_value: String,
+    /// Largest absolute difference in SI units; infinite where only one
+    /// side has a value, only one is NaN or non-numeric values differ.
+    pub max_deviation: f64,
+    /// First step with the largest difference.
+    pub max_step: usize,
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(unit) = self.unit.as_deref().filter(|unit| !unit.is_empty()) {
+            write!(f, " [{unit}]")?;
+        }
+        write!(
+            f,
+            ": {} instead of {} at step {}, max deviation {} at step {}",
+            self.current_value,
+            self.baseline_value,
+            self.first_step,
+            self.max_deviation,
+            self.max_step
+        )
+    }
+}
+
+/// Result of a [`Diff`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiffReport {
+    /// States present on both sides whose values differ, in baseline order.
+    pub changed: Vec<StateDiff>,
+    /// Paths of states only present in the current side.
+    pub added: Vec<String>,
+    /// Paths of states only present in the baseline.
+    pub missing: Vec<String>,
+}
+
+impl DiffReport {
+    /// Whether both sides match within tolerance.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.missing.is_empty()
+    }
+
+    /// The changed state at `path`.
+    pub fn get(&self, path: &str) -> Option<&StateDiff> {
+        self.changed.iter().find(|diff| diff.path == path)
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        write!(
+            f,
+            "{} changed, {} added, {} missing state(s):",
+            self.changed.len(),
+            self.added.len(),
+            self.missing.len()
+        )?;
+        for diff in &self.changed {
+            write!(f, "\n  ~ {diff}")?;
+        }
+        for path in &self.added {
+            write!(f, "\n  + {path}")?;
+        }
+        for path in &self.missing {
+            write!(f, "\n  - {path}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::{Diff, Tolerance};
+    use crate::{ToleranceError, TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+
+    #[derive(Default, TrackedStates)]
+    struct Motor {
+        pwr: TrackedState<Power>,
+        gear: TrackedState<u8>,
+    }
+
+    fn run(pwr: &[f64]) -> Motor {
+        let mut motor = Motor::default();
+        motor.set_recording_matching("*", true);
+        for (i, &p) in pwr.iter().enumerate() {
+            motor.pwr.update(Power::new::<watt>(p));
+            motor.gear.update(i as u8);
+            motor.reset_all();
+        }
+        motor
+    }
+
+    #[test]
+    fn test_that_histories_differ_beyond_tolerance() {
+        let baseline = run(&[1.0, 2.0, 3.0, 4.0]);
+        let current = run(&[1.0, 2.05, 3.5, 4.2]);
+
+        let diff = Diff::new()
+            .with_tolerance("pwr", Tolerance::absolute(Power::new::<watt>(0.1)).unwrap());
+        let report = diff.histories(&baseline, &current).unwrap();
+        assert_eq!(report.changed.len(), 1);
+        let pwr = report.get("pwr").unwrap();
+        assert_eq!((pwr.first_step, pwr.max_step), (2, 2));
+        assert_eq!(pwr.max_deviation, 0.5);
+        assert_eq!(
+            report.to_string(),
+            "1 changed, 0 added, 0 missing state(s):\n  ~ pwr [W]: 3.5 instead of 3 at step 2, max deviation 0.5 at step 2"
+        );
+
+        let diff = diff.with_tolerance("*", Tolerance::relative(0.2));
+        assert!(diff.histories(&baseline, &current).unwrap().is_empty());
+
+        let report = Diff::new().histories(&baseline, &run(&[1.0])).unwrap();
+        assert_eq!(report.get("pwr").unwrap().max_deviation, f64::INFINITY);
+        assert_eq!(report.get("gear").unwrap().first_step, 1);
+    }
+
+    #[test]
+    fn test_that_snapshots_report_added_and_missing_states() {
+        let mut a = TrackedState::<Power>::new("pwr");
+        let mut b = TrackedState::<Power>::new("pwr");
+        let mut flag = TrackedState::<bool>::new("flag");
+        a.update(Power::new::<watt>(1.0));
+        b.update(Power::new::<watt>(1.0 + 1e-12));
+        flag.update(true);
+
+        let snapshot = |states: &mut [&mut dyn crate::AnyTracked]| {
+            let mut registry = crate::StateRegistry::new();
+            for state in states {
+                registry.register(*state);
+            }
+            registry.snapshot().unwrap()
+        };
+        let baseline = snapshot(&mut [&mut a, &mut flag]);
+        let current = snapshot(&mut [&mut b]);
+
+        let report = Diff::new()
+            .with_default(Tolerance::relative(1e-9))
+            .snapshots(&baseline, &current)
+            .unwrap();
+        assert!(report.changed.is_empty());
+        assert_eq!(report.missing, ["flag"]);
+        assert!(report.added.is_empty());
+        assert!(
+            !Diff::new()
+                .snapshots(&baseline, &current)
+                .unwrap()
+                .changed
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_that_snapshots_compare_previous_values_and_histories() {
+        let snapshot = |pwr: &[f64], recording: bool| {
+            let mut motor = run(pwr);
+            motor.set_recording_matching("*", recording);
+            motor.snapshot().unwrap()
+        };
+        let baseline = snapshot(&[1.0, 2.0, 3.0], true);
+        assert!(
+            Diff::new()
+                .snapshots(&baseline, &snapshot(&[1.0, 2.0, 3.0], true))
+                .unwrap()
+                .is_empty()
+        );
+
+        let report = Diff::new()
+            .snapshots(
+                &snapshot(&[1.0, 2.0, 3.0], false),
+                &snapshot(&[1.0, 2.0, 3.5], false),
+            )
+            .unwrap();
+        let pwr = report.get("pwr").unwrap();
+        assert_eq!((pwr.first_step, pwr.max_deviation), (2, 0.5));
+
+        let report = Diff::new()
+            .snapshots(&baseline, &snapshot(&[1.5, 2.0, 3.0], true))
+            .unwrap();
+        assert_eq!(report.get("pwr").unwrap().first_step, 0);
+        assert!(report.get("gear").is_none());
+    }
+
+    #[test]
+    fn test_that_tolerances_must_match_the_unit_and_be_numeric() {
+        let motor = run(&[1.0]);
+        let err = Diff::new()
+            .with_default(Tolerance::absolute(Power::new::<watt>(0.1)).unwrap())
+            .histories(&motor, &motor)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ToleranceError::UnitMismatch {
+                path: "gear".to_string(),
+                expected: "W".to_string(),
+                found: None,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "tolerance in `W` applied to state `gear` in `no unit`"
+        );
+
+        #[derive(Debug)]
+        struct Label;
+        impl crate::TrackedValue for Label {}
+        assert_eq!(
+            Tolerance::absolute(Label),
+            Err(ToleranceError::NotNumeric {
+                value: "Label".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_that_nan_values_are_equal() {
+        let baseline = run(&[1.0, f64::NAN, f64::INFINITY]);
+        assert!(
+            Diff::new()
+                .histories(&baseline, &run(&[1.0, f64::NAN, f64::INFINITY]))
+                .unwrap()
+                .is_empty()
+        );
+
+        let report = Diff::new()
+            .histories(&baseline, &run(&[1.0, 2.0, f64::INFINITY]))
+            .unwrap();
+        let pwr = report.get("pwr").unwrap();
+        assert_eq!((pwr.first_step, pwr.max_deviation), (1, f64::INFINITY));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::panic::Location;
+
+/// Misuse of a [`TrackedState`](crate::TrackedState) detected by the fallible
+/// `try_*` methods.
+///
+/// Values are captured via their `Debug` representation so the error does not
+/// depend on the state's value type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrackedStateError {
+    /// The state was updated more than once in the same step.
+    DoubleUpdate {
+        name: String,
+        step: usize,
+        old: String,
+        new: String,
+        /// Call site of the original update, if known.
+        first: Option<&'static Location<'static>>,
+        /// Call site of the offending update.
+        second: &'static Location<'static>,
+    },
+    /// The state was not updated before being checked.
+    NotUpdated { name: String, step: usize },
+    /// The state was read before it was updated in the current step.
+    ReadBeforeUpdate {
+        name: String,
+        step: usize,
+        /// Call site of the read.
+        at: &'static Location<'static>,
+    },
+    /// An iteration was started on a state already updated in the current
+    /// step.
+    UpdatedBeforeIterating {
+        name: String,
+        step: usize,
+        /// Call site of the update, if known.
+        at: Option<&'static Location<'static>>,
+    },
+}
+
+impl TrackedStateError {
+    /// Name of the state that caused the error.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::DoubleUpdate { name, .. }
+            | Self::NotUpdated { name, .. }
+            | Self::ReadBeforeUpdate { name, .. }
+            | Self::UpdatedBeforeIterating { name, .. } => name,
+        }
+    }
+
+    /// Step index at which the error occurred.
+    pub fn step(&self) -> usize {
+        match self {
+            Self::DoubleUpdate { step, .. }
+            | Self::NotUpdated { step, .. }
+            | Self::ReadBeforeUpdate { step, .. }
+            | Self::UpdatedBeforeIterating { step, .. } => *step,
+        }
+    }
+}
+
+impl fmt::Display for TrackedStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.name() {
+            "" => "unnamed state".to_string(),
+            name => format!("state `{name}`"),
+        };
+        match self {
+            Self::DoubleUpdate {
+                step,
+                old,
+                new,
+                first,
+                second,
+                ..
+            } => {
+                write!(
+                    f,
+                    "{state} was updated twice in step {step} (old: {old}, new: {new}); "
+                )?;
+                match first {
+                    Some(first) => write!(f, "first updated at {first}, ")?,
+                    None => write!(f, "first update site unknown, ")?,
+                }
+                write!(f, "updated again at {second}")
+            }
+            Self::NotUpdated { step, .. } => {
+                write!(f, "{state} was not updated in step {step}")
+            }
+            Self::ReadBeforeUpdate { step, at, .. } => write!(
+                f,
+                "{state} was read before being updated in step {step} at {at}"
+            ),
+            Self::UpdatedBeforeIterating { step, at, .. } => {
+                write!(f, "{state} was already updated in step {step}")?;
+                if let Some(at) = at {
+                    write!(f, " at {at}")?;
+                }
+                write!(f, " before iterating")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TrackedStateError {}
+
+/// Mismatch between a [`Snapshot`](crate::Snapshot) and the states it is
+/// restored into.  Nothing is restored when this is returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// A state has no entry in the snapshot.
+    MissingState { path: String },
+    /// The snapshot has an entry for which there is no state.
+    UnknownState { path: String },
+    /// Several states have the same path, so they cannot be told apart.
+    DuplicatePath { path: String },
+    /// The snapshot entry was taken from a state of another type.
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingState { path } => write!(f, "state `{path}` is not in the snapshot"),
+            Self::UnknownState { path } => {
+                write!(f, "snapshot entry `{path}` does not match any state")
+            }
+            Self::DuplicatePath { path } => {
+                write!(f, "more than one state has the path `{path}`")
+            }
+            Self::TypeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "state `{path}` is a `{expected}` but the snapshot holds a `{found}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// A [`Tolerance`](crate::Tolerance) that cannot be applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ToleranceError {
+    /// The absolute tolerance has no value in SI units.
+    NotNumeric { value: String },
+    /// An absolute tolerance was applied to a state in another unit.
+    UnitMismatch {
+        path: String,
+        expected: String,
+        found: Option<String>,
+    },
+}
+
+impl fmt::Display for ToleranceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotNumeric { value } => write!(f, "tolerance {value} is not numeric"),
+            Self::UnitMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "tolerance in `{expected}` applied to state `{path}` in `{}`",
+                found.as_deref().unwrap_or("no unit")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ToleranceError {}
@@ -0,0 +1,87 @@
+//! Writers for recorded [`History`](crate::History)s.
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod csv;
+
+#[cfg(feature = "arrow")]
+pub use arrow::ArrowExporter;
+pub use csv::CsvWriter;
+
+use std::fmt;
+
+use crate::{History, TrackedState, TrackedStates, TrackedValue, path, value};
+
+/// A history converted to `f64` values in a given unit.
+#[derive(Debug)]
+struct Column {
+    name: String,
+    unit: String,
+    values: Vec<Option<f64>>,
+}
+
+impl Column {
+    /// Values of `history` in SI units.
+    fn si<T>(name: &str, history: &History<T>) -> Self
+    where
+        T: TrackedValue,
+    {
+        Self {
+            name: name.to_string(),
+            unit: T::unit().unwrap_or_default(),
+            values: history.iter().map(|v| v.and_then(T::to_si)).collect(),
+        }
+    }
+
+    /// Values of `history` converted by `convert`.
+    fn with<T>(name: &str, history: &History<T>, unit: &str, convert: impl Fn(&T) -> f64) -> Self {
+        Self {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            values: history.iter().map(|v| v.map(&convert)).collect(),
+        }
+    }
+
+    /// Values of the history of `state` in SI units, labelled with its unit.
+    /// Empty if recording is disabled, and missing if the value type is not
+    /// [registered](crate::value::register).
+    fn state<T>(state: &TrackedState<T>) -> Self
+    where
+        T: fmt::Debug + 'static,
+    {
+        let convert = value::si_converter::<T>();
+        Self {
+            name: state.name().to_string(),
+            unit: state.unit().unwrap_or_default().to_string(),
+            values: state.history().map_or_else(Vec::new, |history| {
+                history
+                    .iter()
+                    .map(|v| v.and_then(|v| convert.as_ref().and_then(|convert| convert(v))))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Columns for the states in `states` matching the glob `pattern` that
+/// record history, named by path and in SI units.
+fn select(states: &dyn TrackedStates, pattern: &str) -> Vec<Column> {
+    let mut columns = Vec::new();
+    states.for_each("", &mut |p, state| {
+        if path::matches(pattern, p)
+            && let Some(history) = state.si_history()
+        {
+            columns.push(Column {
+                name: p.to_string(),
+                unit: state.unit().unwrap_or_default(),
+                values: history.as_slice().to_vec(),
+            });
+        }
+    });
+    columns
+}
+
+/// Number of steps in the longest column.
+fn steps(columns: &[Column]) -> usize {
+    columns.iter().map(|c| c.values.len()).max().unwrap_or(0)
+}
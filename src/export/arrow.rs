@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+use super::Column;
+use crate::{History, TrackedState, TrackedStates, TrackedValue};
+
+/// Field metadata key holding the unit of a column.
+pub const UNIT_METADATA_KEY: &str = "unit";
+
+/// Converts histories into an Arrow record batch, one `Float64` column per
+/// state after a `step` column.
+///
+/// Columns are named after the state and carry their unit in the field
+/// metadata under [`UNIT_METADATA_KEY`].  Steps in which a state was not
+/// updated are null.  With the `parquet` feature the histories can be written
+/// directly to a Parquet file, which keeps the metadata.
+#[derive(Debug, Default)]
+pub struct ArrowExporter {
+    columns: Vec<Column>,
+    #[cfg(feature = "parquet")]
+    row_group_size: Option<usize>,
+}
+
+impl ArrowExporter {
+    /// Creates an exporter without columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a column with the values of `history` in SI units.
+    pub fn column<T>(mut self, name: &str, history: &History<T>) -> Self
+    where
+        T: TrackedValue,
+    {
+        self.columns.push(Column::si(name, history));
+        self
+    }
+
+    /// Adds a column with the values of `history` converted by `convert`,
+    /// labelled with `unit`.
+    pub fn column_with<T>(
+        mut self,
+        name: &str,
+        history: &History<T>,
+        unit: &str,
+        convert: impl Fn(&T) -> f64,
+    ) -> Self {
+        self.columns
+            .push(Column::with(name, history, unit, convert));
+        self
+    }
+
+    /// Adds a column, named by path, for every state in `states` that
+    /// matches the glob `pattern` and records history.
+    pub fn select(mut self, states: &dyn TrackedStates, pattern: &str) -> Self {
+        self.columns.extend(super::select(states, pattern));
+        self
+    }
+
+    /// Adds a column for the history of `state`, named after the state.  The
+    /// column is null if history recording is disabled.
+    pub fn state<T>(mut self, state: &TrackedState<T>) -> Self
+    where
+        T: fmt::Debug + 'static,
+    {
+        self.columns.push(Column::state(state));
+        self
+    }
+
+    /// Schema of the record batch.
+    pub fn schema(&self) -> Schema {
+        let mut fields = vec![Field::new("step", DataType::UInt64, false)];
+        fields.extend(self.columns.iter().map(|c| {
+            Field::new(&c.name, DataType::Float64, true).with_metadata(HashMap::from([(
+                UNIT_METADATA_KEY.to_string(),
+                c.unit.clone(),
+            )]))
+        }));
+        Schema::new(fields)
+    }
+
+    /// Builds a record batch with one row per step.
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        self.record_batch(Arc::new(self.schema()), 0..super::steps(&self.columns))
+    }
+
+    /// Builds a record batch with the rows of `steps`.
+    fn record_batch(
+        &self,
+        schema: Arc<Schema>,
+        steps: Range<usize>,
+    ) -> Result<RecordBatch, ArrowError> {
+        let mut arrays: Vec<ArrayRef> = vec![Arc::new(UInt64Array::from_iter_values(
+            steps.start as u64..steps.end as u64,
+        ))];
+        arrays.extend(self.columns.iter().map(|c| {
+            let values = steps
+                .clone()
+                .map(|step| c.values.get(step).copied().flatten());
+            Arc::new(Float64Array::from_iter(values)) as ArrayRef
+        }));
+        RecordBatch::try_new(schema, arrays)
+    }
+
+    /// Sets the number of steps per Parquet row group, by default
+    /// [`DEFAULT_MAX_ROW_GROUP_SIZE`](parquet::file::properties::DEFAULT_MAX_ROW_GROUP_SIZE).
+    #[cfg(feature = "parquet")]
+    pub fn with_row_group_size(mut self, steps: usize) -> Self {
+        self.row_group_size = Some(steps.max(1));
+        self
+    }
+
+    /// Writes the histories as a Parquet file, one record batch per row
+    /// group, so that only one row group is held in Arrow arrays at a time.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W>(&self, out: W) -> Result<(), parquet::errors::ParquetError>
+    where
+        W: std::io::Write + Send,
+    {
+        use parquet::file::properties::{DEFAULT_MAX_ROW_GROUP_SIZE, WriterProperties};
+
+        let row_group_size = self.row_group_size.unwrap_or(DEFAULT_MAX_ROW_GROUP_SIZE);
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(row_group_size)
+            .build();
+        let schema = Arc::new(self.schema());
+        let mut writer =
+            parquet::arrow::ArrowWriter::try_new(out, schema.clone(), Some(properties))?;
+        let steps = super::steps(&self.columns);
+        for start in (0..steps).step_by(row_group_size) {
+            let end = (start + row_group_size).min(steps);
+            writer.write(&self.record_batch(schema.clone(), start..end)?)?;
+        }
+        writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::{ArrowExporter, UNIT_METADATA_KEY};
+    use crate::TrackedState;
+
+    use arrow_array::{Array, Float64Array};
+    use uom::si::f64::*;
+    use uom::si::power::{kilowatt, watt};
+
+    fn exporter() -> ArrowExporter {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_history();
+        pwr.update(Power::new::<watt>(1500.0));
+        pwr.reset();
+        pwr.reset();
+
+        ArrowExporter::new()
+            .state(&pwr)
+            .column_with("pwr_kw", pwr.history().unwrap(), "kW", |p| {
+                p.get::<kilowatt>()
+            })
+    }
+
+    #[test]
+    fn test_that_record_batch_has_unit_metadata_and_nulls() {
+        let batch = exporter().to_record_batch().unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        assert_eq!(schema.field(1).name(), "pwr");
+        assert_eq!(schema.field(1).metadata()[UNIT_METADATA_KEY], "W");
+        assert_eq!(schema.field(2).metadata()[UNIT_METADATA_KEY], "kW");
+
+        let kw = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(kw.value(0), 1.5);
+        assert!(kw.is_null(1));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_that_parquet_round_trips_unit_metadata() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = std::env::temp_dir().join(format!(
+            "mutation-tracing-{}-arrow-test.parquet",
+            std::process::id()
+        ));
+        exporter()
+            .write_parquet(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batches, vec![exporter().to_record_batch().unwrap()]);
+        assert_eq!(
+            batches[0].schema().field(1).metadata()[UNIT_METADATA_KEY],
+            "W"
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_that_parquet_is_written_in_row_groups() {
+        use arrow_array::UInt64Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = std::env::temp_dir().join(format!(
+            "mutation-tracing-{}-row-group-test.parquet",
+            std::process::id()
+        ));
+        exporter()
+            .with_row_group_size(1)
+            .write_parquet(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        let batches: Vec<_> = builder.build().unwrap().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+        let steps: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| {
+                let steps = batch.column(0).as_any().downcast_ref::<UInt64Array>();
+                steps.unwrap().values().to_vec()
+            })
+            .collect();
+        assert_eq!(steps, [0, 1]);
+    }
+}
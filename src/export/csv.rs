@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use super::Column;
+use crate::{History, TrackedState, TrackedStates, TrackedValue};
+
+/// Writes histories as CSV, one column per state after a `step` column.
+///
+/// Headers are the state name followed by the unit in brackets, e.g.
+/// `pwr [W]`.  By default values are written in SI units; use
+/// [`column_with`](Self::column_with) to pick another unit for a column.
+/// Steps in which a state was not updated are left empty.
+#[derive(Debug, Default)]
+pub struct CsvWriter {
+    columns: Vec<Column>,
+}
+
+impl CsvWriter {
+    /// Creates a writer without columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a column with the values of `history` in SI units.
+    pub fn column<T>(mut self, name: &str, history: &History<T>) -> Self
+    where
+        T: TrackedValue,
+    {
+        self.columns.push(Column::si(name, history));
+        self
+    }
+
+    /// Adds a column with the values of `history` converted by `convert`,
+    /// labelled with `unit`.
+    ///
+    /// ```
+    /// # use mutation_tracing::{History, export::CsvWriter};
+    /// # use uom::si::{f64::Power, power::kilowatt};
+    /// # let history = History::<Power>::new();
+    /// let writer = CsvWriter::new().column_with("pwr", &history, "kW", |p| p.get::<kilowatt>());
+    /// ```
+    pub fn column_with<T>(
+        mut self,
+        name: &str,
+        history: &History<T>,
+        unit: &str,
+        convert: impl Fn(&T) -> f64,
+    ) -> Self {
+        self.columns
+            .push(Column::with(name, history, unit, convert));
+        self
+    }
+
+    /// Adds a column, named by path, for every state in `states` that
+    /// matches the glob `pattern` and records history.
+    pub fn select(mut self, states: &dyn TrackedStates, pattern: &str) -> Self {
+        self.columns.extend(super::select(states, pattern));
+        self
+    }
+
+    /// Adds a column for the history of `state`, named after the state.  The
+    /// column is empty if history recording is disabled.
+    pub fn state<T>(mut self, state: &TrackedState<T>) -> Self
+    where
+        T: fmt::Debug + 'static,
+    {
+        self.columns.push(Column::state(state));
+        self
+    }
+
+    /// Writes the header and one row per step.
+    pub fn write<W: io::Write>(&self, mut out: W) -> io::Result<()> {
+        let mut header = vec!["step".to_string()];
+        header.extend(self.columns.iter().map(|c| {
+            let label = if c.unit.is_empty() {
+                c.name.clone()
+            } else {
+                format!("{} [{}]", c.name, c.unit)
+            };
+            escape(&label).into_owned()
+        }));
+        writeln!(out, "{}", header.join(","))?;
+
+        for step in 0..super::steps(&self.columns) {
+            let mut row = step.to_string();
+            for column in &self.columns {
+                row.push(',');
+                if let Some(Some(value)) = column.values.get(step) {
+                    row.push_str(&value.to_string());
+                }
+            }
+            writeln!(out, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes a field if it contains a separator, quote or line break.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Splits a line written by [`CsvWriter`] into unquoted fields.
+pub(crate) fn split(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::CsvWriter;
+    use crate::{TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::{kilowatt, watt};
+    use uom::si::time::second;
+
+    #[test]
+    fn test_that_csv_has_unit_headers_and_step_rows() {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_history();
+        let mut energy = TrackedState::<Energy>::new("energy").with_history();
+        let mut dt = TrackedState::<Time>::new("dt").with_history();
+        let mut soc = TrackedState::<f64>::new("soc, total").with_history();
+
+        for i in 0..2 {
+            pwr.update(Power::new::<watt>(1500.0 * (i + 1) as f64));
+            dt.update(Time::new::<second>(0.5));
+            if i == 1 {
+                energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+            }
+            soc.update(0.9);
+            pwr.reset();
+            energy.reset();
+            dt.reset();
+            soc.reset();
+        }
+
+        let mut out = Vec::new();
+        CsvWriter::new()
+            .state(&pwr)
+            .column_with("pwr", pwr.history().unwrap(), "kW", |p| p.get::<kilowatt>())
+            .state(&energy)
+            .state(&dt)
+            .state(&soc)
+            .write(&mut out)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "step,pwr [W],pwr [kW],energy [J],dt [s],\"soc, total\"\n\
+             0,1500,1.5,,0.5,0.9\n\
+             1,3000,3,1500,0.5,0.9\n"
+        );
+    }
+
+    #[test]
+    fn test_that_select_adds_recorded_states_by_glob() {
+        #[derive(Default, TrackedStates)]
+        struct Motor {
+            pwr: TrackedState<Power>,
+            dt: TrackedState<Time>,
+        }
+
+        let mut motor = Motor::default();
+        motor.set_recording_matching("*", true);
+        motor.pwr.update(Power::new::<watt>(2.0));
+        motor.dt.update(Time::new::<second>(1.0));
+        motor.reset_all();
+
+        let mut out = Vec::new();
+        CsvWriter::new()
+            .select(&motor, "p*")
+            .write(&mut out)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "step,pwr [W]\n0,2\n");
+    }
+}
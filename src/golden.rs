@@ -0,0 +1,358 @@
+//! Regression tests against recorded golden traces.
+//!
+//! A golden trace is a CSV file, as written by
+//! [`CsvWriter`](crate::export::CsvWriter), holding the history of every
+//! state of a model in SI units.  The first run records it; later runs are
+//! compared against it with a [`Diff`].  States of value types without an SI
+//! representation cannot be held by the trace, so they must not record
+//! history.  Set the environment variable
+//! [`UPDATE_ENV`] to `1` to record it again after an intended change.
+//!
+//! ```no_run
+//! # use mutation_tracing::{TrackedState, TrackedStates, golden::Golden};
+//! # #[derive(Default, TrackedStates)]
+//! # struct Model { soc: TrackedState<f64> }
+//! let mut model = Model::default();
+//! model.set_recording_matching("**", true);
+//! // ... run the model ...
+//! Golden::new("tests/golden/model.csv").check(&model);
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::{env, fmt};
+
+use crate::diff::{Sample, Series};
+use crate::export::{CsvWriter, csv};
+use crate::{Diff, DiffReport, ToleranceError, TrackedStates};
+
+/// Environment variable which, when set to `1`, makes
+/// [`Golden::check`] rewrite golden files instead of comparing against them.
+pub const UPDATE_ENV: &str = "MUTATION_TRACING_UPDATE_GOLDEN";
+
+/// A golden trace file and the tolerances to compare against it with.
+#[derive(Clone, Debug)]
+pub struct Golden {
+    path: PathBuf,
+    diff: Diff,
+}
+
+/// What [`Golden::try_check`] did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    /// The file did not exist and was recorded.
+    Recorded,
+    /// The file was rewritten because [`UPDATE_ENV`] is set.
+    Updated,
+    /// The run matched the file within tolerance.
+    Matched,
+}
+
+/// Failure of [`Golden::try_check`].
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The file is not a valid trace.
+    Parse { line: usize, message: String },
+    /// The run differs from the file.
+    Mismatch(DiffReport),
+    /// A state recording history has no values in SI units, see
+    /// [`AnyTracked::is_numeric`](crate::AnyTracked::is_numeric).
+    NotNumeric { path: String },
+    /// A tolerance of the diff does not fit a state.
+    Tolerance(ToleranceError),
+}
+
+impl Golden {
+    /// A golden trace stored at `path`, compared exactly.  Relative paths
+    /// are resolved against the working directory, which for `cargo test`
+    /// is the package root.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            diff: Diff::new(),
+        }
+    }
+
+    /// Compares with the tolerances of `diff`.
+    pub fn with_diff(mut self, diff: Diff) -> Self {
+        self.diff = diff;
+        self
+    }
+
+    /// Path of the golden file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the histories of `states` if the file does not exist or
+    /// [`UPDATE_ENV`] is set, and otherwise compares them against it.  Only
+    /// states recording history are included, and all of them must be
+    /// numeric.
+    pub fn try_check(&self, states: &dyn TrackedStates) -> Result<GoldenOutcome, GoldenError> {
+        check_numeric(states)?;
+        let update = env::var(UPDATE_ENV).is_ok_and(|value| value == "1");
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(_) if update => return self.record(states, GoldenOutcome::Updated),
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return self.record(states, GoldenOutcome::Recorded);
+            }
+            Err(err) => return Err(GoldenError::Io(err)),
+        };
+        let report = self
+            .diff
+            .series(&parse(&contents)?, &Series::histories(states))
+            .map_err(GoldenError::Tolerance)?;
+        if report.is_empty() {
+            Ok(GoldenOutcome::Matched)
+        } else {
+            Err(GoldenError::Mismatch(report))
+        }
+    }
+
+    /// Like [`try_check`](Self::try_check), but panics with the per-state
+    /// differences on failure.
+    #[track_caller]
+    pub fn check(&self, states: &dyn TrackedStates) {
+        if let Err(err) = self.try_check(states) {
+            panic!("golden trace {}: {err}", self.path.display());
+        }
+    }
+
+    fn record(
+        &self,
+        states: &dyn TrackedStates,
+        outcome: GoldenOutcome,
+    ) -> Result<GoldenOutcome, GoldenError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(GoldenError::Io)?;
+        }
+        let file = fs::File::create(&self.path).map_err(GoldenError::Io)?;
+        CsvWriter::new()
+            .select(states, "**")
+            .write(io::BufWriter::new(file))
+            .map_err(GoldenError::Io)?;
+        Ok(outcome)
+    }
+}
+
+/// Fails for the first state recording history that is not numeric.
+fn check_numeric(states: &dyn TrackedStates) -> Result<(), GoldenError> {
+    let mut result = Ok(());
+    states.for_each("", &mut |path, state| {
+        if result.is_ok() && !state.is_numeric() && state.si_history().is_some() {
+            result = Err(GoldenError::NotNumeric {
+                path: path.to_string(),
+            });
+        }
+    });
+    result
+}
+
+/// Reads the columns of a trace written by [`CsvWriter`], whose first
+/// column numbers the rows from step 0.
+fn parse(contents: &str) -> Result<Vec<Series>, GoldenError> {
+    let mut lines = contents.lines().enumerate();
+    let header = match lines.next() {
+        Some((_, header)) => csv::split(header),
+        None => return Ok(Vec::new()),
+    };
+    if header[0] != "step" {
+        return Err(GoldenError::Parse {
+            line: 1,
+            message: format!("expected a `step` column, found `{}`", header[0]),
+        });
+    }
+    let mut series: Vec<Series> = header
+        .iter()
+        .skip(1)
+        .map(|label| {
+            let (path, unit) = match label.strip_suffix(']').and_then(|l| l.rsplit_once(" [")) {
+                Some((path, unit)) => (path, Some(unit.to_string())),
+                None => (label.as_str(), None),
+            };
+            Series {
+                path: path.to_string(),
+                unit,
+                start: 0,
+                values: Vec::new(),
+            }
+        })
+        .collect();
+
+    for (i, line) in lines {
+        let error = |message: String| GoldenError::Parse {
+            line: i + 1,
+            message,
+        };
+        let fields = csv::split(line);
+        if fields.len() != header.len() {
+            return Err(error(format!(
+                "expected {} fields, found {}",
+                header.len(),
+                fields.len()
+            )));
+        }
+        let step = i - 1;
+        if fields[0] != step.to_string() {
+            return Err(error(format!(
+                "expected step {step}, found `{}`",
+                fields[0]
+            )));
+        }
+        for (series, field) in series.iter_mut().zip(&fields[1..]) {
+            series.values.push(match field.as_str() {
+                "" => Sample::Missing,
+                field => Sample::Number(
+                    field
+                        .parse()
+                        .map_err(|_| error(format!("invalid number `{field}`")))?,
+                ),
+            });
+        }
+    }
+    Ok(series)
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+            Self::Mismatch(report) => {
+                write!(f, "{report}\nset {UPDATE_ENV}=1 to accept the new values")
+            }
+            Self::Tolerance(err) => write!(f, "{err}"),
+            Self::NotNumeric { path } => write!(
+                f,
+                "state `{path}` records history but has no values in SI units"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Tolerance(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::{Golden, GoldenError, GoldenOutcome, parse};
+    use crate::diff::{Diff, Tolerance};
+    use crate::{TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+
+    #[derive(Default, TrackedStates)]
+    struct Motor {
+        pwr: TrackedState<Power>,
+        soc: TrackedState<f64>,
+    }
+
+    fn run(scale: f64) -> Motor {
+        let mut motor = Motor::default();
+        motor.set_recording_matching("**", true);
+        for i in 0..3 {
+            motor.pwr.update(Power::new::<watt>(scale * i as f64));
+            if i > 0 {
+                motor.soc.update(1.0 / 3.0);
+            }
+            motor.reset_all();
+        }
+        motor
+    }
+
+    #[test]
+    fn test_that_golden_trace_is_recorded_then_compared() {
+        let path = std::env::temp_dir().join(format!(
+            "mutation-tracing-{}-golden-test/motor.csv",
+            std::process::id()
+        ));
+        let golden = Golden::new(&path);
+
+        assert_eq!(
+            golden.try_check(&run(1.0)).unwrap(),
+            GoldenOutcome::Recorded
+        );
+        assert_eq!(golden.try_check(&run(1.0)).unwrap(), GoldenOutcome::Matched);
+
+        let err = golden.try_check(&run(1.01)).unwrap_err();
+        let GoldenError::Mismatch(report) = &err else {
+            panic!("{err}");
+        };
+        assert_eq!(report.changed.len(), 1);
+        assert!(err.to_string().contains(
+            "~ pwr [W]: 1.01 instead of 1 at step 1, max deviation 0.020000000000000018 at step 2"
+        ));
+
+        let golden = golden.with_diff(Diff::new().with_tolerance("pwr", Tolerance::relative(0.02)));
+        assert_eq!(
+            golden.try_check(&run(1.01)).unwrap(),
+            GoldenOutcome::Matched
+        );
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_that_step_column_is_validated() {
+        assert_eq!(
+            parse("step,pwr [W]\n0,1\n1,2\n").unwrap()[0].values.len(),
+            2
+        );
+
+        let message = |contents| match parse(contents) {
+            Err(GoldenError::Parse { line, message }) => (line, message),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(
+            message("time,pwr [W]\n0,1\n"),
+            (1, "expected a `step` column, found `time`".to_string())
+        );
+        assert_eq!(
+            message("step,pwr [W]\n0,1\n2,2\n"),
+            (3, "expected step 1, found `2`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_that_non_numeric_states_are_not_recorded() {
+        #[derive(Clone, Debug)]
+        enum Gear {
+            Neutral,
+        }
+
+        #[derive(Default, TrackedStates)]
+        struct Gearbox {
+            gear: TrackedState<Gear>,
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "mutation-tracing-{}-golden-text-test/gearbox.csv",
+            std::process::id()
+        ));
+        let mut gearbox = Gearbox::default();
+        gearbox.gear.update(Gear::Neutral);
+        let golden = Golden::new(&path);
+        assert_eq!(golden.try_check(&gearbox).unwrap(), GoldenOutcome::Recorded);
+
+        gearbox.set_recording_matching("**", true);
+        let err = golden.try_check(&gearbox).unwrap_err();
+        assert!(matches!(&err, GoldenError::NotNumeric { path } if path == "gear"));
+        assert_eq!(
+            err.to_string(),
+            "state `gear` records history but has no values in SI units"
+        );
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use std::slice;
+
+/// Per-step values of a [`TrackedState`](crate::TrackedState), indexed by step.
+///
+/// Steps in which the state was not updated are stored as `None`, and so are
+/// the steps before recording [`start`](Self::start)ed.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct History<T> {
+    start: usize,
+    steps: Vec<Option<T>>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a history whose first recorded step is `step`.
+    pub fn starting_at(step: usize) -> Self {
+        Self {
+            start: step,
+            steps: std::iter::repeat_with(|| None).take(step).collect(),
+        }
+    }
+
+    /// First recorded step; earlier steps are `None`.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Converts every value with `f`, keeping the start step.
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> Option<U>) -> History<U> {
+        History {
+            start: self.start,
+            steps: self.iter().map(|v| v.and_then(&mut f)).collect(),
+        }
+    }
+
+    /// Appends the value of the next step.
+    pub fn push(&mut self, value: Option<T>) {
+        self.steps.push(value);
+    }
+
+    /// Number of steps up to the last recorded one.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Whether no steps have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Value recorded at `step`, if the step exists and the state was updated.
+    pub fn get(&self, step: usize) -> Option<&T> {
+        self.steps.get(step).and_then(Option::as_ref)
+    }
+
+    /// Value recorded at the final step.
+    pub fn last(&self) -> Option<&T> {
+        self.steps.last().and_then(Option::as_ref)
+    }
+
+    /// The last `n` recorded steps, or all of them if fewer were recorded.
+    pub fn last_n(&self, n: usize) -> &[Option<T>] {
+        &self.steps[self.steps.len().saturating_sub(n)..]
+    }
+
+    /// All recorded steps in order.
+    pub fn as_slice(&self) -> &[Option<T>] {
+        &self.steps
+    }
+
+    /// Iterates over the recorded steps in order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.steps.iter(),
+        }
+    }
+}
+
+impl<T> FromIterator<Option<T>> for History<T> {
+    fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> Self {
+        Self {
+            start: 0,
+            steps: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a History<T> {
+    type Item = Option<&'a T>;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the steps of a [`History`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T> {
+    inner: slice::Iter<'a, Option<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = Option<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(Option::as_ref)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(Option::as_ref)
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn test_that_history_is_indexed_by_step() {
+        let mut history = History::new();
+        history.push(Some(1.0));
+        history.push(None);
+        history.push(Some(3.0));
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0), Some(&1.0));
+        assert_eq!(history.get(1), None);
+        assert_eq!(history.get(5), None);
+        assert_eq!(history.last(), Some(&3.0));
+        assert_eq!(history.last_n(2), &[None, Some(3.0)]);
+        assert_eq!(history.last_n(10).len(), 3);
+        assert_eq!(
+            history.iter().collect::<Vec<_>>(),
+            vec![Some(&1.0), None, Some(&3.0)]
+        );
+    }
+
+    #[test]
+    fn test_that_late_history_is_padded_to_its_start() {
+        let mut history = History::starting_at(2);
+        history.push(Some(3.0));
+
+        assert_eq!(history.start(), 2);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(2), Some(&3.0));
+        assert_eq!(history.map(|v| Some(v * 2.0)).get(2), Some(&6.0));
+    }
+}
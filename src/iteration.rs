@@ -0,0 +1,148 @@
+use std::fmt;
+use std::panic::Location;
+
+use crate::TrackedState;
+
+/// Convergence information of a state solved in an [`Iteration`] scope.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IterationStats {
+    /// Number of updates made inside the scope.
+    pub iterations: usize,
+    /// Residual reported with the last update, if any.
+    pub residual: Option<f64>,
+}
+
+/// A scope in which a [`TrackedState`] may be updated repeatedly, e.g. once
+/// per iteration of an implicit solver.
+///
+/// Created by [`TrackedState::iterate`].  When the scope ends, through
+/// [`converge`](Self::converge) or by being dropped, the last value is sealed:
+/// any later update in the same step is a double update again.
+#[derive(Debug)]
+pub struct Iteration<'a, T: fmt::Debug> {
+    state: &'a mut TrackedState<T>,
+    stats: IterationStats,
+}
+
+impl<'a, T> Iteration<'a, T>
+where
+    T: fmt::Debug,
+{
+    pub(crate) fn new(state: &'a mut TrackedState<T>) -> Self {
+        Self {
+            state,
+            stats: IterationStats::default(),
+        }
+    }
+
+    /// Replaces the value of the state, counting one iteration.
+    #[track_caller]
+    pub fn update(&mut self, value: T) {
+        self.state.set(value, Location::caller());
+        self.stats.iterations += 1;
+    }
+
+    /// Replaces the value of the state and records the solver residual.
+    #[track_caller]
+    pub fn update_with_residual(&mut self, value: T, residual: f64) {
+        self.update(value);
+        self.stats.residual = Some(residual);
+    }
+
+    /// Value of the latest iteration.  Not a read of the state, so it is
+    /// neither a read violation nor a dependency.
+    pub fn get(&self) -> Option<&T> {
+        self.state.current()
+    }
+
+    /// Iterations and residual so far.
+    pub fn stats(&self) -> IterationStats {
+        self.stats
+    }
+
+    /// Ends the scope, sealing the value, and returns the final statistics.
+    pub fn converge(self) -> IterationStats {
+        self.stats
+    }
+}
+
+impl<T> Drop for Iteration<'_, T>
+where
+    T: fmt::Debug,
+{
+    fn drop(&mut self) {
+        self.state.seal_iteration(self.stats);
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::IterationStats;
+    use crate::{TrackedState, TrackedStateError};
+
+    #[test]
+    fn test_that_iteration_allows_repeated_updates_until_sealed() {
+        let mut temp = TrackedState::<f64>::new("temp");
+
+        let mut scope = temp.iterate();
+        let mut x = 1.0;
+        for _ in 0..20 {
+            let next = (x + 2.0 / x) / 2.0;
+            scope.update_with_residual(next, next - x);
+            x = next;
+            if (next * next - 2.0).abs() < 1e-12 {
+                break;
+            }
+        }
+        let stats = scope.converge();
+
+        assert!(stats.iterations > 1);
+        assert_eq!(temp.iteration_stats(), Some(stats));
+        assert!((temp.get().unwrap() - 2.0_f64.sqrt()).abs() < 1e-12);
+        assert!(temp.try_update(0.0).is_err());
+        temp.check();
+
+        temp.reset();
+        assert_eq!(temp.iteration_stats(), None);
+        temp.iterate().update(1.0);
+        assert_eq!(
+            temp.iteration_stats(),
+            Some(IterationStats {
+                iterations: 1,
+                residual: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_that_empty_iteration_leaves_state_untouched() {
+        let mut temp = TrackedState::<f64>::new("temp");
+        drop(temp.iterate());
+        assert_eq!(temp.iteration_stats(), None);
+        assert!(temp.try_check().is_err());
+
+        temp.try_iterate().unwrap().update(1.0);
+        assert!(matches!(
+            temp.try_iterate(),
+            Err(TrackedStateError::UpdatedBeforeIterating { step: 0, .. })
+        ));
+        assert!(temp.try_update(2.0).is_err());
+    }
+
+    #[test]
+    fn test_that_iteration_get_is_not_a_read() {
+        let mut temp = TrackedState::<f64>::new("temp").with_strict_reads();
+        let (_, graph) = crate::dependency::record(|| {
+            let mut scope = temp.iterate();
+            assert_eq!(scope.get(), None);
+            scope.update(1.0);
+            assert_eq!(scope.get(), Some(&1.0));
+        });
+
+        assert!(temp.read_violations().is_empty());
+        temp.check();
+        assert_eq!(graph.inputs("temp").map(<[_]>::len), Some(0));
+    }
+}
@@ -0,0 +1,53 @@
+//! Tracking of state variables that must be updated exactly once per step.
+//!
+//! Building with `RUSTFLAGS="--cfg mutation_tracing_untracked"` replaces
+//! [`TrackedState`] by a version with the same API that keeps only the
+//! values, name, unit and step and does no checks, for optimized production
+//! runs.  This is a cfg rather than a feature so
+//! that no dependency can turn tracking off for the whole build.
+
+// Lets the derive macros refer to `::mutation_tracing` from inside this crate.
+extern crate self as mutation_tracing;
+
+pub mod accumulator;
+pub mod any_tracked;
+pub mod bisect;
+pub mod dependency;
+pub mod diff;
+pub mod error;
+pub mod export;
+pub mod golden;
+pub mod history;
+pub mod iteration;
+pub mod path;
+pub mod policy;
+pub mod report;
+pub mod snapshot;
+pub mod step;
+#[cfg(feature = "tracing")]
+pub mod trace;
+#[cfg(not(mutation_tracing_untracked))]
+pub mod tracked_state;
+#[cfg(mutation_tracing_untracked)]
+#[path = "untracked.rs"]
+pub mod tracked_state;
+pub mod tracked_states;
+pub mod value;
+
+pub use accumulator::TrackedAccumulator;
+pub use any_tracked::{AnyTracked, StateRegistry};
+pub use bisect::Bisection;
+pub use dependency::DependencyGraph;
+pub use diff::{Diff, DiffReport, Tolerance};
+pub use error::{SnapshotError, ToleranceError, TrackedStateError};
+pub use history::History;
+pub use iteration::{Iteration, IterationStats};
+pub use policy::DoubleUpdatePolicy;
+pub use report::{CheckFailure, CheckReport};
+pub use snapshot::{Snapshot, StateSnapshot};
+pub use step::StepGuard;
+pub use tracked_state::{ReadViolation, TrackedState};
+pub use tracked_states::TrackedStates;
+pub use value::TrackedValue;
+
+pub use mutation_tracing_derive::TrackedStates;
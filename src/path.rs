@@ -0,0 +1,56 @@
+//! Dotted state paths such as `vehicle.motor.pwr` and glob patterns over
+//! them.
+
+/// Whether `path` matches the glob `pattern`.
+///
+/// Both are split at dots.  In the pattern, a `**` segment matches any number
+/// of segments, including none, and within a segment `*` matches any run of
+/// characters and `?` a single character.  For example `vehicle.*.pwr`
+/// matches `vehicle.motor.pwr` but not `vehicle.motor.inverter.pwr`, which
+/// `vehicle.**.pwr` also matches.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let path: Vec<&str> = path.split('.').collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path)) => {
+                matches_segment(segment.as_bytes(), name.as_bytes()) && matches_segments(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| matches_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && matches_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches_segment(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_that_globs_match_segments() {
+        assert!(matches("vehicle.motor.pwr", "vehicle.motor.pwr"));
+        assert!(matches("vehicle.*.pwr", "vehicle.motor.pwr"));
+        assert!(!matches("vehicle.*.pwr", "vehicle.motor.inverter.pwr"));
+        assert!(matches("vehicle.**.pwr", "vehicle.motor.inverter.pwr"));
+        assert!(matches("vehicle.**.pwr", "vehicle.pwr"));
+        assert!(matches("**", "vehicle.motor.pwr"));
+        assert!(matches("vehicle.motor.p*", "vehicle.motor.pwr_max"));
+        assert!(matches("vehicle.motor.?wr", "vehicle.motor.pwr"));
+        assert!(!matches("vehicle.motor", "vehicle.motor.pwr"));
+        assert!(!matches("vehicle.*", "vehicle"));
+    }
+}
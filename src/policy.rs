@@ -0,0 +1,80 @@
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::TrackedStateError;
+
+/// What happens when a [`TrackedState`](crate::TrackedState) is updated
+/// again in the same step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DoubleUpdatePolicy {
+    /// `update` panics and `try_update` fails.
+    #[default]
+    Panic,
+    /// `update` keeps the first value and records the error, which is then
+    /// reported by `check`; `try_update` fails.
+    Error,
+    /// The new value replaces the old one after passing the error to the
+    /// sink set with [`set_warning_sink`], which prints it to standard error
+    /// by default, or emits it as a `WARN` event with the `tracing` feature.
+    Warn,
+    /// The new value silently replaces the old one.
+    Overwrite,
+    /// Updating with an equal value is ignored; a different value is treated
+    /// like [`Panic`](Self::Panic).
+    ///
+    /// Values need not implement `PartialEq`, so they are equal if their
+    /// `Debug` representations are.  For floats this means that `NaN` equals
+    /// `NaN` and that `0.0` differs from `-0.0`.
+    IgnoreIfEqual,
+}
+
+/// Global override, stored as the policy's index plus one, or 0 if unset.
+static GLOBAL_POLICY: AtomicU8 = AtomicU8::new(0);
+
+const POLICIES: [DoubleUpdatePolicy; 5] = [
+    DoubleUpdatePolicy::Panic,
+    DoubleUpdatePolicy::Error,
+    DoubleUpdatePolicy::Warn,
+    DoubleUpdatePolicy::Overwrite,
+    DoubleUpdatePolicy::IgnoreIfEqual,
+];
+
+/// Overrides the policy of every state, or restores the per-state policies
+/// with `None`.
+pub fn set_global_policy(policy: Option<DoubleUpdatePolicy>) {
+    let index = policy.map_or(0, |p| {
+        POLICIES.iter().position(|&q| q == p).unwrap() as u8 + 1
+    });
+    GLOBAL_POLICY.store(index, Ordering::Relaxed);
+}
+
+/// The global override set by [`set_global_policy`], if any.
+pub fn global_policy() -> Option<DoubleUpdatePolicy> {
+    match GLOBAL_POLICY.load(Ordering::Relaxed) {
+        0 => None,
+        index => Some(POLICIES[index as usize - 1]),
+    }
+}
+
+/// Receives the double updates allowed by [`DoubleUpdatePolicy::Warn`].
+pub type WarningSink = Box<dyn Fn(&TrackedStateError) + Send + Sync>;
+
+static WARNING_SINK: RwLock<Option<WarningSink>> = RwLock::new(None);
+
+/// Sends warnings to `sink`, or back to the default with `None`.
+pub fn set_warning_sink(sink: Option<WarningSink>) {
+    *WARNING_SINK.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+
+/// Reports a double update allowed by [`DoubleUpdatePolicy::Warn`].
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn warn(err: &TrackedStateError) {
+    match &*WARNING_SINK.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(sink) => sink(err),
+        #[cfg(feature = "tracing")]
+        None => tracing::warn!(target: crate::trace::TARGET, error = %err, "double update"),
+        #[cfg(not(feature = "tracing"))]
+        None => eprintln!("warning: {err}"),
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::{TrackedStateError, path};
+
+/// A state that failed its check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckFailure {
+    /// Dotted path of the state, e.g. `vehicle.motor.pwr`.
+    pub path: String,
+    /// Path of the component owning the state, empty at the top level.
+    pub component: String,
+    /// Step in which the check failed.
+    pub step: usize,
+    /// `Debug` representation of the last known value: the current one if
+    /// the state was updated, otherwise the previous step's.
+    pub last_value: Option<String>,
+    /// Why the check failed.
+    pub error: TrackedStateError,
+}
+
+impl CheckFailure {
+    /// Creates a failure for the state at `path`, deriving the component from
+    /// the path.
+    pub fn new(
+        path: impl Into<String>,
+        last_value: Option<String>,
+        error: TrackedStateError,
+    ) -> Self {
+        let path = path.into();
+        let component = match path.rsplit_once('.') {
+            Some((component, _)) => component.to_string(),
+            None => String::new(),
+        };
+        Self {
+            path,
+            component,
+            step: error.step(),
+            last_value,
+            error,
+        }
+    }
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if !self.component.is_empty() {
+            write!(f, " in `{}`", self.component)?;
+        }
+        write!(f, " at step {}", self.step)?;
+        match &self.last_value {
+            Some(value) => write!(f, " (last value {value})")?,
+            None => write!(f, " (no value)")?,
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+/// Every failed check across a model, collected by
+/// [`TrackedStates::check_report`](crate::TrackedStates::check_report).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Failures in the order the states were visited.
+    pub failures: Vec<CheckFailure>,
+}
+
+impl CheckReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether every check passed.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Adds a failure.
+    pub fn push(&mut self, failure: CheckFailure) {
+        self.failures.push(failure);
+    }
+
+    /// Paths of the failed states.
+    pub fn paths(&self) -> Vec<&str> {
+        self.failures.iter().map(|f| f.path.as_str()).collect()
+    }
+
+    /// Failures of the states whose path matches the glob `pattern`, see
+    /// [`path::matches`].
+    pub fn select(&self, pattern: &str) -> Self {
+        Self {
+            failures: self
+                .failures
+                .iter()
+                .filter(|f| path::matches(pattern, &f.path))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns `Err(self)` if any check failed.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_ok() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failures.len() {
+            0 => write!(f, "all state checks passed"),
+            n => {
+                write!(f, "{n} state check(s) failed:")?;
+                for failure in &self.failures {
+                    write!(f, "\n  {failure}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckReport {}
@@ -0,0 +1,351 @@
+//! Checkpoints of the full tracked state of a model.
+
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{AnyTracked, History, SnapshotError};
+
+/// The full state of a single [`AnyTracked`], taken by
+/// [`AnyTracked::save_state`].
+///
+/// Besides the opaque copy used for restoring, it keeps the current and
+/// previous value in SI units and as text, and the history in SI units, for
+/// inspection.
+pub struct StateSnapshot {
+    name: String,
+    step: usize,
+    updated: bool,
+    si_value: Option<f64>,
+    debug_value: Option<String>,
+    prev_si_value: Option<f64>,
+    prev_debug_value: Option<String>,
+    si_history: Option<History<f64>>,
+    unit: Option<String>,
+    type_name: &'static str,
+    type_id: TypeId,
+    data: Box<dyn Any>,
+}
+
+impl StateSnapshot {
+    /// Captures `data`, the private part of `state`.
+    pub(crate) fn new<S, D>(state: &S, data: D) -> Self
+    where
+        S: AnyTracked + 'static,
+        D: 'static,
+    {
+        Self {
+            name: state.name().to_string(),
+            step: state.step(),
+            updated: state.is_updated(),
+            si_value: state.si_value(),
+            debug_value: state.debug_value(),
+            prev_si_value: state.prev_si_value(),
+            prev_debug_value: state.prev_debug_value(),
+            si_history: state.si_history(),
+            unit: state.unit(),
+            type_name: std::any::type_name::<S>(),
+            type_id: TypeId::of::<S>(),
+            data: Box::new(data),
+        }
+    }
+
+    /// The private part of a state of type `S`.
+    pub(crate) fn data<S, D>(&self) -> Result<&D, SnapshotError>
+    where
+        S: 'static,
+        D: 'static,
+    {
+        self.data
+            .downcast_ref()
+            .filter(|_| self.type_id == TypeId::of::<S>())
+            .ok_or_else(|| SnapshotError::TypeMismatch {
+                path: self.name.clone(),
+                expected: std::any::type_name::<S>(),
+                found: self.type_name,
+            })
+    }
+
+    /// Name of the state when it was captured.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Step counter of the state.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Whether the state had been updated in its current step.
+    pub fn is_updated(&self) -> bool {
+        self.updated
+    }
+
+    /// Value in SI base units, see [`AnyTracked::si_value`].
+    pub fn si_value(&self) -> Option<f64> {
+        self.si_value
+    }
+
+    /// `Debug` representation of the value.
+    pub fn debug_value(&self) -> Option<&str> {
+        self.debug_value.as_deref()
+    }
+
+    /// Previous step's value in SI base units, see
+    /// [`AnyTracked::prev_si_value`].
+    pub fn prev_si_value(&self) -> Option<f64> {
+        self.prev_si_value
+    }
+
+    /// `Debug` representation of the previous step's value.
+    pub fn prev_debug_value(&self) -> Option<&str> {
+        self.prev_debug_value.as_deref()
+    }
+
+    /// History in SI units, if the state was recording.
+    pub fn si_history(&self) -> Option<&History<f64>> {
+        self.si_history.as_ref()
+    }
+
+    /// Unit of the value, see [`AnyTracked::unit`].
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Type name of the captured state, e.g. `TrackedState<f64>`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Debug for StateSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateSnapshot")
+            .field("name", &self.name)
+            .field("step", &self.step)
+            .field("updated", &self.updated)
+            .field("value", &self.debug_value)
+            .field("prev", &self.prev_debug_value)
+            .field("unit", &self.unit)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Owned copy of the full state of a model: current and previous values,
+/// update status, step counters and histories of every state, by path.
+///
+/// Taken with [`TrackedStates::snapshot`](crate::TrackedStates::snapshot) or
+/// [`StateRegistry::snapshot`](crate::StateRegistry::snapshot) and put back
+/// with the matching `restore`, which may be called any number of times to
+/// branch from the same checkpoint.  A state restored as updated in its
+/// current step still rejects a second update.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    states: Vec<(String, StateSnapshot)>,
+}
+
+impl Snapshot {
+    /// Captures every state yielded by `for_each`, failing if two of them
+    /// have the same path.
+    pub(crate) fn capture<'a>(
+        for_each: impl FnOnce(&mut dyn FnMut(&str, &'a dyn AnyTracked)),
+    ) -> Result<Self, SnapshotError> {
+        let mut states = Vec::new();
+        for_each(&mut |path, state| states.push((path.to_string(), state.save_state())));
+        check_unique(states.iter().map(|(path, _)| path))?;
+        Ok(Self { states })
+    }
+
+    /// Restores every state in `states` from the entry with the same path,
+    /// after checking that the paths and types match.
+    pub(crate) fn restore_into(
+        &self,
+        mut states: Vec<(String, &mut dyn AnyTracked)>,
+    ) -> Result<(), SnapshotError> {
+        check_unique(states.iter().map(|(path, _)| path))?;
+        for (path, state) in &states {
+            let snapshot = self
+                .get(path)
+                .ok_or_else(|| SnapshotError::MissingState { path: path.clone() })?;
+            if state.as_any().type_id() != snapshot.type_id {
+                return Err(SnapshotError::TypeMismatch {
+                    path: path.clone(),
+                    expected: state.type_name(),
+                    found: snapshot.type_name,
+                });
+            }
+        }
+        if let Some((path, _)) = self
+            .states
+            .iter()
+            .find(|(path, _)| !states.iter().any(|(p, _)| p == path))
+        {
+            return Err(SnapshotError::UnknownState { path: path.clone() });
+        }
+        for (path, state) in &mut states {
+            state.restore_state(self.get(path).unwrap())?;
+        }
+        Ok(())
+    }
+
+    /// Number of captured states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether no states were captured.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The captured state at `path`.
+    pub fn get(&self, path: &str) -> Option<&StateSnapshot> {
+        self.states
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, state)| state)
+    }
+
+    /// Captured states with their paths, in capture order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &StateSnapshot)> {
+        self.states
+            .iter()
+            .map(|(path, state)| (path.as_str(), state))
+    }
+}
+
+/// Fails with the first path that occurs twice.
+fn check_unique<'a>(paths: impl Iterator<Item = &'a String>) -> Result<(), SnapshotError> {
+    let mut seen = HashSet::new();
+    for path in paths {
+        if !seen.insert(path) {
+            return Err(SnapshotError::DuplicatePath { path: path.clone() });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use crate::{
+        Snapshot, SnapshotError, StateRegistry, TrackedAccumulator, TrackedState,
+        TrackedStateError, TrackedStates,
+    };
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[derive(Default, TrackedStates)]
+    struct Battery {
+        pwr: TrackedState<Power>,
+        soc: TrackedState<f64>,
+        energy: TrackedAccumulator<Energy>,
+    }
+
+    impl Battery {
+        fn step(&mut self, watts: f64) {
+            let dt = Time::new::<second>(1.0);
+            self.pwr.update(Power::new::<watt>(watts));
+            self.energy.add(*self.pwr.get().unwrap() * dt);
+            let soc = self.soc.get_prev().copied().unwrap_or(1.0);
+            self.soc.update(soc - watts / 1000.0);
+        }
+    }
+
+    #[test]
+    fn test_that_restore_branches_from_a_checkpoint() {
+        let mut battery = Battery::default();
+        battery.soc.enable_history();
+        battery.step(100.0);
+        battery.reset_all();
+        battery.pwr.update(Power::new::<watt>(50.0));
+
+        let snapshot = battery.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 3);
+        let pwr = snapshot.get("pwr").unwrap();
+        assert!(pwr.is_updated());
+        assert_eq!(
+            (pwr.step(), pwr.si_value(), pwr.unit()),
+            (1, Some(50.0), Some("W"))
+        );
+
+        battery.reset_all();
+        battery.step(200.0);
+        battery.reset_all();
+
+        for _ in 0..2 {
+            battery.restore(&snapshot).unwrap();
+            assert_eq!(battery.pwr.step(), 1);
+            assert!(matches!(
+                battery.pwr.try_update(Power::new::<watt>(1.0)),
+                Err(TrackedStateError::DoubleUpdate { .. })
+            ));
+            assert_eq!(battery.soc.get(), None);
+            assert_eq!(battery.soc.get_prev(), Some(&0.9));
+            assert_eq!(battery.soc.history().unwrap().len(), 1);
+            assert_eq!(battery.energy.total().value, 100.0);
+        }
+    }
+
+    #[test]
+    fn test_that_snapshots_are_not_recorded_as_reads() {
+        let mut battery = Battery::default();
+        battery.name_states("");
+        battery.step(100.0);
+        battery.reset_all();
+
+        let (_, graph) = crate::dependency::record(|| {
+            battery.pwr.update(Power::new::<watt>(50.0));
+            let snapshot = battery.snapshot().unwrap();
+            assert_eq!(snapshot.get("soc").unwrap().prev_si_value(), Some(0.9));
+            battery.soc.update(0.85);
+        });
+
+        assert_eq!(graph.inputs("soc").map(<[_]>::len), Some(0));
+    }
+
+    #[test]
+    fn test_that_mismatched_snapshots_are_rejected() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        let mut soc = TrackedState::<f64>::new("soc");
+        let snapshot = {
+            let mut registry = StateRegistry::new();
+            registry.register(&mut pwr);
+            registry.snapshot().unwrap()
+        };
+
+        let mut registry = StateRegistry::new();
+        registry.register(&mut soc);
+        assert_eq!(
+            registry.restore(&snapshot),
+            Err(SnapshotError::MissingState {
+                path: "soc".to_string()
+            })
+        );
+
+        soc.set_name("pwr");
+        let mut registry = StateRegistry::new();
+        registry.register(&mut soc);
+        assert!(matches!(
+            registry.restore(&snapshot),
+            Err(SnapshotError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_that_duplicate_paths_are_rejected() {
+        let mut soc = TrackedState::<f64>::default();
+        let mut dt = TrackedState::<f64>::default();
+        let mut registry = StateRegistry::new();
+        registry.register(&mut soc);
+        registry.register(&mut dt);
+        let duplicate = Err(SnapshotError::DuplicatePath {
+            path: String::new(),
+        });
+
+        assert_eq!(registry.snapshot().map(|_| ()), duplicate);
+        assert_eq!(registry.restore(&Snapshot::default()), duplicate);
+    }
+}
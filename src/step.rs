@@ -0,0 +1,123 @@
+use std::fmt;
+use std::thread;
+
+use crate::{CheckReport, TrackedStates};
+
+/// Checks and resets a set of registered states at the end of a step.
+///
+/// Call [`finish`](Self::finish) to get a [`CheckReport`] listing every state
+/// that failed its check.  A guard dropped without being finished does the
+/// same and panics with the full list if any state is missing, unless the
+/// thread is already panicking.
+#[derive(Default)]
+pub struct StepGuard<'a> {
+    states: Vec<(String, &'a mut dyn TrackedStates)>,
+    finished: bool,
+}
+
+impl<'a> StepGuard<'a> {
+    /// Creates a guard without registered states.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers states to be checked and reset, see
+    /// [`register`](Self::register).
+    pub fn with(mut self, prefix: &str, states: &'a mut dyn TrackedStates) -> Self {
+        self.register(prefix, states);
+        self
+    }
+
+    /// Registers states to be checked and reset.  States are reported by
+    /// their path, which for states without a name is prefixed with
+    /// `prefix`, see [`TrackedStates`].
+    pub fn register(&mut self, prefix: &str, states: &'a mut dyn TrackedStates) {
+        self.states.push((prefix.to_string(), states));
+    }
+
+    /// Checks all registered states, resets them and reports every failure.
+    pub fn finish(mut self) -> CheckReport {
+        self.run()
+    }
+
+    fn run(&mut self) -> CheckReport {
+        self.finished = true;
+        let mut report = CheckReport::new();
+        for (prefix, states) in &mut self.states {
+            #[cfg(feature = "tracing")]
+            let _span = crate::trace::component_span(prefix).entered();
+            states.collect_failures(prefix, &mut report);
+            states.reset_all();
+        }
+        report
+    }
+}
+
+impl Drop for StepGuard<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let report = self.run();
+        if !report.is_ok() && !thread::panicking() {
+            panic!("{report}");
+        }
+    }
+}
+
+impl fmt::Debug for StepGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StepGuard")
+            .field(
+                "prefixes",
+                &self.states.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            )
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use super::StepGuard;
+    use crate::{TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[derive(Default, TrackedStates)]
+    struct Motor {
+        pwr: TrackedState<Power>,
+        dt: TrackedState<Time>,
+    }
+
+    #[test]
+    fn test_that_finish_reports_every_missing_state_and_resets() {
+        let mut motor = Motor::default();
+        let mut energy = TrackedState::<Energy>::new("energy");
+        motor.pwr.update(Power::new::<watt>(1.0));
+
+        let report = StepGuard::new()
+            .with("motor", &mut motor)
+            .with("", &mut energy)
+            .finish();
+
+        assert_eq!(report.paths(), ["motor.dt", "energy"]);
+        assert_eq!(motor.pwr.get_prev(), Some(&Power::new::<watt>(1.0)));
+        assert_eq!(energy.step(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "1 state check(s) failed:\n  pwr at step 0")]
+    fn test_that_drop_panics_with_missing_states() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        let mut dt = TrackedState::<Time>::new("dt");
+        dt.update(Time::new::<second>(1.0));
+
+        let mut guard = StepGuard::new();
+        guard.register("", &mut pwr);
+        guard.register("", &mut dt);
+    }
+}
@@ -0,0 +1,164 @@
+//! Events and spans for the [`tracing`] crate, enabled by the `tracing`
+//! feature.
+//!
+//! Every update, reset and check of a [`TrackedState`](crate::TrackedState)
+//! emits an event with target [`TARGET`] and the fields `state`, `step`,
+//! `value`, `unit` and `location` where known.  Updates and resets are
+//! logged at `TRACE`, passed checks at `DEBUG`, and failed checks and double
+//! updates allowed by [`DoubleUpdatePolicy::Warn`](crate::DoubleUpdatePolicy)
+//! at `WARN` with an `error` field.  Wrap model code in [`step_span`] and
+//! [`component_span`] so subscribers can filter and group the events.
+
+use std::fmt;
+use std::panic::Location;
+
+use tracing::Span;
+
+use crate::TrackedStateError;
+
+/// Target of all events emitted by this crate.
+pub const TARGET: &str = "mutation_tracing";
+
+/// A span for the simulation step `step`.
+pub fn step_span(step: usize) -> Span {
+    tracing::info_span!(target: TARGET, "step", step)
+}
+
+/// A span for the component at `path`, e.g. `vehicle.motor`.
+///
+/// [`StepGuard`](crate::StepGuard) checks and resets each registered set of
+/// states inside such a span, named by its prefix.
+pub fn component_span(path: &str) -> Span {
+    tracing::info_span!(target: TARGET, "component", path)
+}
+
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn update<T>(
+    name: &str,
+    unit: Option<&str>,
+    step: usize,
+    value: &T,
+    location: &Location<'_>,
+) where
+    T: fmt::Debug,
+{
+    tracing::trace!(
+        target: TARGET,
+        state = name,
+        step,
+        value = ?value,
+        unit,
+        location = %location,
+        "update"
+    );
+}
+
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn reset<T>(name: &str, unit: Option<&str>, step: usize, value: Option<&T>)
+where
+    T: fmt::Debug,
+{
+    tracing::trace!(
+        target: TARGET,
+        state = name,
+        step,
+        value = ?value,
+        unit,
+        "reset"
+    );
+}
+
+#[cfg_attr(mutation_tracing_untracked, allow(dead_code))]
+pub(crate) fn check<T>(
+    name: &str,
+    unit: Option<&str>,
+    step: usize,
+    value: Option<&T>,
+    result: &Result<(), TrackedStateError>,
+) where
+    T: fmt::Debug,
+{
+    match result {
+        Ok(()) => tracing::debug!(
+            target: TARGET,
+            state = name,
+            step,
+            value = ?value,
+            unit,
+            "check passed"
+        ),
+        Err(error) => tracing::warn!(
+            target: TARGET,
+            state = name,
+            step,
+            value = ?value,
+            unit,
+            error = %error,
+            "check failed"
+        ),
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::{component_span, step_span};
+    use crate::{DoubleUpdatePolicy, TrackedState};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_that_mutations_emit_events_within_spans() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _step = step_span(0).entered();
+            let _motor = component_span("motor").entered();
+            let mut pwr = TrackedState::<Power>::new("motor.pwr");
+            pwr.update(Power::new::<watt>(2.0));
+            pwr.check();
+            pwr.reset();
+            let _ = pwr.try_check();
+            let mut soc = TrackedState::<f64>::new("soc").with_policy(DoubleUpdatePolicy::Warn);
+            soc.update(0.5);
+            soc.update(0.4);
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 7, "{output}");
+        assert!(lines[0].starts_with("TRACE step{step=0}:component{path=\"motor\"}"));
+        assert!(lines[0].contains("update state=\"motor.pwr\" step=0"));
+        assert!(lines[0].contains("unit=\"W\""));
+        assert!(lines[0].contains(&format!("location={}:", file!())));
+        assert!(lines[1].contains("DEBUG") && lines[1].contains("check passed"));
+        assert!(lines[2].contains("reset"));
+        assert!(lines[3].contains("WARN") && lines[3].contains("was not updated in step 1"));
+        assert!(lines[5].contains("WARN") && lines[5].contains("double update"));
+        assert!(lines[5].contains("state `soc` was updated twice in step 0"));
+    }
+}
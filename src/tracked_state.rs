@@ -0,0 +1,811 @@
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::Location;
+
+#[cfg(feature = "tracing")]
+use crate::trace;
+use crate::{
+    DoubleUpdatePolicy, History, Iteration, IterationStats, TrackedStateError, dependency, policy,
+    value,
+};
+
+/// A state variable that must be updated exactly once between resets.
+///
+/// With the `serde` feature, call sites, backtraces, read violations,
+/// double-update errors and iteration statistics are not serialized, as most
+/// of them point into the source code.  A state that fails its check only
+/// because of a read violation or a double update under
+/// [`DoubleUpdatePolicy::Error`] passes it after a round trip.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedState<T: fmt::Debug> {
+    value: Option<T>,
+    prev: Option<T>,
+    name: String,
+    unit: Option<String>,
+    step: usize,
+    history: Option<History<T>>,
+    committed: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    updated_at: Option<&'static Location<'static>>,
+    capture_backtraces: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    backtrace: Option<Backtrace>,
+    strict_reads: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    read_violations: RefCell<Vec<ReadViolation>>,
+    policy: DoubleUpdatePolicy,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    errors: Vec<TrackedStateError>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    iteration: Option<IterationStats>,
+}
+
+/// The step-to-step part of a [`TrackedState`], see [`TrackedState::save`].
+#[derive(Clone, Debug)]
+pub(crate) struct SavedState<T> {
+    value: Option<T>,
+    prev: Option<T>,
+    step: usize,
+    history: Option<History<T>>,
+    committed: bool,
+    updated_at: Option<&'static Location<'static>>,
+    read_violations: Vec<ReadViolation>,
+    errors: Vec<TrackedStateError>,
+    iteration: Option<IterationStats>,
+}
+
+/// A read of a [`TrackedState`] before it was updated in the same step,
+/// recorded in strict read mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadViolation {
+    /// Step in which the read happened.
+    pub step: usize,
+    /// Call site of the read.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for ReadViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read before update in step {} at {}",
+            self.step, self.location
+        )
+    }
+}
+
+impl<T> Default for TrackedState<T>
+where
+    T: fmt::Debug + 'static,
+{
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl<T> TrackedState<T>
+where
+    T: fmt::Debug,
+{
+    /// Creates a state with the given name, used in diagnostics.  Values of
+    /// a [registered](crate::value::register) type label the state with
+    /// their unit.
+    pub fn new(name: impl Into<String>) -> Self
+    where
+        T: 'static,
+    {
+        Self {
+            value: None,
+            prev: None,
+            name: name.into(),
+            unit: value::unit_of::<T>(),
+            step: 0,
+            history: None,
+            committed: false,
+            updated_at: None,
+            capture_backtraces: false,
+            backtrace: None,
+            strict_reads: false,
+            read_violations: RefCell::default(),
+            policy: DoubleUpdatePolicy::default(),
+            errors: Vec::new(),
+            iteration: None,
+        }
+    }
+
+    /// Sets what happens on a second update in the same step.
+    pub fn with_policy(mut self, policy: DoubleUpdatePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Policy in effect, taking the global override into account.
+    pub fn policy(&self) -> DoubleUpdatePolicy {
+        policy::global_policy().unwrap_or(self.policy)
+    }
+
+    /// Double updates recorded in the current step under
+    /// [`DoubleUpdatePolicy::Error`].  Not serialized.
+    pub fn errors(&self) -> &[TrackedStateError] {
+        &self.errors
+    }
+
+    /// Records every [`get`](Self::get) of a not-yet-updated value as a
+    /// [`ReadViolation`], which then fails [`check`](Self::check).
+    pub fn with_strict_reads(mut self) -> Self {
+        self.strict_reads = true;
+        self
+    }
+
+    /// Reads before update recorded in strict read mode, across all steps.
+    /// Not serialized.
+    pub fn read_violations(&self) -> Vec<ReadViolation> {
+        self.read_violations.borrow().clone()
+    }
+
+    /// Captures a backtrace on every update, see [`backtrace`](Self::backtrace).
+    pub fn with_backtraces(mut self) -> Self {
+        self.capture_backtraces = true;
+        self
+    }
+
+    /// Enables history recording, see [`enable_history`](Self::enable_history).
+    pub fn with_history(mut self) -> Self {
+        self.enable_history();
+        self
+    }
+
+    /// Records the value of every step into a history when the state is
+    /// reset or the step is committed.
+    pub fn enable_history(&mut self) {
+        let step = self.step;
+        self.history
+            .get_or_insert_with(|| History::starting_at(step));
+    }
+
+    /// Values recorded so far, if history recording is enabled.
+    pub fn history(&self) -> Option<&History<T>> {
+        self.history.as_ref()
+    }
+
+    /// Name of the state, empty if none was given.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Renames the state.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    /// Labels the state with the unit of its value, used in dependency
+    /// graphs, tracing events and exports.  Overrides the unit of a
+    /// registered value type.
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.set_unit(unit);
+        self
+    }
+
+    /// Changes the unit label, see [`with_unit`](Self::with_unit).
+    pub fn set_unit(&mut self, unit: impl Into<String>) {
+        self.unit = Some(unit.into());
+    }
+
+    /// Unit label of the state, if any.
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Number of resets since the state was created.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Call site of the update in the current step.
+    pub fn updated_at(&self) -> Option<&'static Location<'static>> {
+        self.updated_at
+    }
+
+    /// Backtrace of the update in the current step, if backtraces are
+    /// captured.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    /// Sets the value for the current step.
+    ///
+    /// A second update in the same step is handled according to the
+    /// [`policy`](Self::policy); by default it panics, naming both call sites.
+    #[track_caller]
+    pub fn update(&mut self, value: T) {
+        if let Err(err) = self.try_update(value) {
+            if self.policy() == DoubleUpdatePolicy::Error {
+                self.errors.push(err);
+                return;
+            }
+            match &self.backtrace {
+                Some(backtrace) => panic!("{err}\nfirst update backtrace:\n{backtrace}"),
+                None => panic!("{err}"),
+            }
+        }
+    }
+
+    /// Sets the value for the current step, failing on a second update in
+    /// the same step unless the [`policy`](Self::policy) allows it.
+    #[track_caller]
+    pub fn try_update(&mut self, value: T) -> Result<(), TrackedStateError> {
+        let caller = Location::caller();
+        if let Some(old) = &self.value {
+            let policy = self.policy();
+            if policy != DoubleUpdatePolicy::Overwrite {
+                let (old, new) = (format!("{old:?}"), format!("{value:?}"));
+                if policy == DoubleUpdatePolicy::IgnoreIfEqual && old == new {
+                    return Ok(());
+                }
+                let err = TrackedStateError::DoubleUpdate {
+                    name: self.name.clone(),
+                    step: self.step,
+                    old,
+                    new,
+                    first: self.updated_at,
+                    second: caller,
+                };
+                if policy != DoubleUpdatePolicy::Warn {
+                    return Err(err);
+                }
+                policy::warn(&err);
+            }
+        }
+        self.set(value, caller);
+        Ok(())
+    }
+
+    /// Stores `value` as updated at `caller`, without any checks.
+    pub(crate) fn set(&mut self, value: T, caller: &'static Location<'static>) {
+        #[cfg(feature = "tracing")]
+        trace::update(&self.name, self.unit(), self.step, &value, caller);
+        self.value = Some(value);
+        self.updated_at = Some(caller);
+        dependency::on_update(&self.name, self.unit());
+        if self.capture_backtraces {
+            self.backtrace = Some(Backtrace::force_capture());
+        }
+    }
+
+    /// Starts an [`Iteration`] scope in which the state may be updated
+    /// repeatedly until it converges.  Panics if the state was already
+    /// updated in this step.
+    #[track_caller]
+    pub fn iterate(&mut self) -> Iteration<'_, T> {
+        match self.try_iterate() {
+            Ok(iteration) => iteration,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Starts an [`Iteration`] scope, failing if the state was already
+    /// updated in this step.
+    pub fn try_iterate(&mut self) -> Result<Iteration<'_, T>, TrackedStateError> {
+        if self.value.is_some() {
+            return Err(TrackedStateError::UpdatedBeforeIterating {
+                name: self.name.clone(),
+                step: self.step,
+                at: self.updated_at,
+            });
+        }
+        Ok(Iteration::new(self))
+    }
+
+    /// Records the statistics of a finished [`Iteration`].  A scope without
+    /// updates leaves the state as if it had never been started.
+    pub(crate) fn seal_iteration(&mut self, stats: IterationStats) {
+        if stats.iterations > 0 {
+            self.iteration = Some(stats);
+        }
+    }
+
+    /// Statistics of the [`Iteration`] that produced the current value.
+    pub fn iteration_stats(&self) -> Option<IterationStats> {
+        self.iteration
+    }
+
+    /// Panics if the state has not been updated since the last reset, was
+    /// updated twice under [`DoubleUpdatePolicy::Error`], or was read before
+    /// being updated in strict read mode.
+    pub fn check(&self) {
+        if let Err(err) = self.try_check() {
+            panic!("{err}");
+        }
+    }
+
+    /// Fails if the state has not been updated since the last reset, was
+    /// updated twice under [`DoubleUpdatePolicy::Error`], or was read before
+    /// being updated in strict read mode.
+    pub fn try_check(&self) -> Result<(), TrackedStateError> {
+        let result = self.check_result();
+        #[cfg(feature = "tracing")]
+        trace::check(
+            &self.name,
+            self.unit(),
+            self.step,
+            self.value.as_ref(),
+            &result,
+        );
+        result
+    }
+
+    fn check_result(&self) -> Result<(), TrackedStateError> {
+        if self.value.is_none() {
+            return Err(TrackedStateError::NotUpdated {
+                name: self.name.clone(),
+                step: self.step,
+            });
+        }
+        if let Some(err) = self.errors.first() {
+            return Err(err.clone());
+        }
+        let violations = self.read_violations.borrow();
+        match violations.iter().find(|v| v.step == self.step) {
+            Some(violation) => Err(TrackedStateError::ReadBeforeUpdate {
+                name: self.name.clone(),
+                step: self.step,
+                at: violation.location,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the value for the current step, if it has been updated.
+    ///
+    /// In strict read mode, reading a value that has not been updated yet is
+    /// recorded as a [`ReadViolation`].
+    #[track_caller]
+    pub fn get(&self) -> Option<&T> {
+        dependency::on_read(&self.name, self.unit(), false, self.value.is_some());
+        if self.strict_reads && self.value.is_none() {
+            self.read_violations.borrow_mut().push(ReadViolation {
+                step: self.step,
+                location: Location::caller(),
+            });
+        }
+        self.value.as_ref()
+    }
+
+    /// Current value, or the previous step's if not updated yet, without
+    /// counting as a read.
+    pub(crate) fn last_known(&self) -> Option<&T> {
+        self.value.as_ref().or(self.prev())
+    }
+
+    /// Value for the current step, without counting as a read.
+    pub(crate) fn current(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Returns the value of the previous step, if it was updated.
+    ///
+    /// Unlike [`get`](Self::get), this never counts as a read of the current
+    /// step.
+    pub fn get_prev(&self) -> Option<&T> {
+        dependency::on_read(&self.name, self.unit(), true, true);
+        self.prev()
+    }
+
+    /// Value of the previous step, without counting as a read.  With history
+    /// enabled, it is the last recorded value once the history covers the
+    /// previous step.
+    pub(crate) fn prev(&self) -> Option<&T> {
+        match &self.history {
+            Some(history) if self.step > history.start() => history.get(self.step - 1),
+            _ => self.prev.as_ref(),
+        }
+    }
+
+    /// Returns the value for the current step, failing if it has not been
+    /// updated yet.
+    #[track_caller]
+    pub fn try_get(&self) -> Result<&T, TrackedStateError> {
+        let caller = Location::caller();
+        dependency::on_read(&self.name, self.unit(), false, self.value.is_some());
+        self.value
+            .as_ref()
+            .ok_or_else(|| TrackedStateError::ReadBeforeUpdate {
+                name: self.name.clone(),
+                step: self.step,
+                at: caller,
+            })
+    }
+
+    /// Clears the value so the state can be updated in the next step.
+    ///
+    /// The value is kept as the previous step's value, see
+    /// [`get_prev`](Self::get_prev).  With history enabled, it is moved into
+    /// the history instead, unless the step was already committed.
+    pub fn reset(&mut self) {
+        #[cfg(feature = "tracing")]
+        trace::reset(&self.name, self.unit(), self.step, self.value.as_ref());
+        let value = self.value.take();
+        match &mut self.history {
+            Some(history) => {
+                if !self.committed {
+                    history.push(value);
+                }
+                self.prev = None;
+            }
+            None => self.prev = value,
+        }
+        self.committed = false;
+        self.updated_at = None;
+        self.backtrace = None;
+        self.errors.clear();
+        self.iteration = None;
+        self.step += 1;
+    }
+}
+
+impl<T> TrackedState<T>
+where
+    T: fmt::Debug + Clone,
+{
+    /// Stops recording history, returning the values recorded so far.
+    pub fn disable_history(&mut self) -> Option<History<T>> {
+        self.prev = self.prev().cloned();
+        self.committed = false;
+        self.history.take()
+    }
+
+    /// Records the current value into the history without resetting.
+    ///
+    /// Does nothing if history is disabled or the step was already committed.
+    pub fn commit_step(&mut self) {
+        if let Some(history) = &mut self.history
+            && !self.committed
+        {
+            history.push(self.value.clone());
+            self.committed = true;
+        }
+    }
+
+    /// Copies everything that changes from step to step, for a
+    /// [`Snapshot`](crate::Snapshot).
+    pub(crate) fn save(&self) -> SavedState<T> {
+        SavedState {
+            value: self.value.clone(),
+            prev: self.prev.clone(),
+            step: self.step,
+            history: self.history.clone(),
+            committed: self.committed,
+            updated_at: self.updated_at,
+            read_violations: self.read_violations.borrow().clone(),
+            errors: self.errors.clone(),
+            iteration: self.iteration,
+        }
+    }
+
+    /// Puts back what [`save`](Self::save) copied.  The name and settings
+    /// are kept; a captured backtrace is dropped.
+    pub(crate) fn load(&mut self, saved: &SavedState<T>) {
+        let saved = saved.clone();
+        self.value = saved.value;
+        self.prev = saved.prev;
+        self.step = saved.step;
+        self.history = saved.history;
+        self.committed = saved.committed;
+        self.updated_at = saved.updated_at;
+        self.backtrace = None;
+        self.read_violations = RefCell::new(saved.read_violations);
+        self.errors = saved.errors;
+        self.iteration = saved.iteration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrackedState;
+    use crate::{DoubleUpdatePolicy, TrackedStateError};
+
+    // Import uom for demonstration
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[test]
+    #[should_panic]
+    fn test_that_update_can_happen_only_once() {
+        let mut pwr = TrackedState::<Power>::default();
+        let mut energy = TrackedState::<Energy>::default();
+        let mut dt = TrackedState::<Time>::default();
+
+        pwr.update(Power::new::<watt>(1.0));
+        dt.update(Time::new::<second>(1.0));
+        energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+
+        pwr.update(Power::new::<watt>(2.0));
+    }
+
+    #[test]
+    fn test_that_reset_and_check_work() {
+        let mut pwr = TrackedState::<Power>::default();
+        let mut energy = TrackedState::<Energy>::default();
+        let mut dt = TrackedState::<Time>::default();
+
+        pwr.update(Power::new::<watt>(1.0));
+        dt.update(Time::new::<second>(1.0));
+        energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+
+        pwr.check();
+        dt.check();
+        energy.check();
+
+        pwr.reset();
+        dt.reset();
+        energy.reset();
+
+        pwr.update(Power::new::<watt>(1.0));
+        dt.update(Time::new::<second>(1.0));
+        energy.update(*pwr.get().unwrap() * *dt.get().unwrap());
+
+        pwr.check();
+        dt.check();
+        energy.check();
+    }
+
+    #[test]
+    fn test_that_try_variants_report_errors() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+
+        assert_eq!(
+            pwr.try_check(),
+            Err(TrackedStateError::NotUpdated {
+                name: "pwr".into(),
+                step: 0
+            })
+        );
+        assert!(matches!(
+            pwr.try_get(),
+            Err(TrackedStateError::ReadBeforeUpdate { step: 0, .. })
+        ));
+
+        pwr.reset();
+        pwr.try_update(Power::new::<watt>(1.0)).unwrap();
+        let err = pwr.try_update(Power::new::<watt>(2.0)).unwrap_err();
+        assert_eq!(err.name(), "pwr");
+        assert_eq!(err.step(), 1);
+        match err {
+            TrackedStateError::DoubleUpdate { old, new, .. } => {
+                assert_eq!(old, format!("{:?}", Power::new::<watt>(1.0)));
+                assert_eq!(new, format!("{:?}", Power::new::<watt>(2.0)));
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+        assert_eq!(pwr.get(), Some(&Power::new::<watt>(1.0)));
+    }
+
+    #[test]
+    fn test_that_registered_values_label_the_unit() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        assert_eq!(pwr.unit(), Some("W"));
+        pwr.set_name("motor.pwr");
+        assert_eq!(pwr.unit(), Some("W"));
+        assert_eq!(TrackedState::<Time>::default().unit(), Some("s"));
+        assert_eq!(TrackedState::<f64>::new("soc").unit(), None);
+        assert_eq!(TrackedState::<String>::new("label").unit(), None);
+        assert_eq!(
+            TrackedState::<f64>::new("soc").with_unit("%").unit(),
+            Some("%")
+        );
+    }
+
+    #[test]
+    fn test_that_history_records_each_step() {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_history();
+
+        pwr.update(Power::new::<watt>(1.0));
+        pwr.reset();
+        pwr.reset();
+        pwr.update(Power::new::<watt>(3.0));
+        pwr.commit_step();
+        pwr.commit_step();
+        pwr.reset();
+
+        let history = pwr.history().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0), Some(&Power::new::<watt>(1.0)));
+        assert_eq!(history.get(1), None);
+        assert_eq!(history.last(), Some(&Power::new::<watt>(3.0)));
+        assert!(TrackedState::<Power>::default().history().is_none());
+    }
+
+    #[test]
+    fn test_that_history_enabled_mid_run_starts_at_the_current_step() {
+        let mut pwr = TrackedState::<Power>::new("pwr");
+        pwr.update(Power::new::<watt>(1.0));
+        pwr.reset();
+        pwr.reset();
+
+        pwr.enable_history();
+        pwr.update(Power::new::<watt>(3.0));
+        pwr.reset();
+
+        let history = pwr.history().unwrap();
+        assert_eq!(history.start(), 2);
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(2), Some(&Power::new::<watt>(3.0)));
+    }
+
+    #[test]
+    fn test_that_double_update_names_both_call_sites() {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_backtraces();
+
+        pwr.update(Power::new::<watt>(1.0));
+        let first = pwr.updated_at().unwrap();
+        assert_eq!(first.file(), file!());
+        assert_eq!(first.line(), line!() - 3);
+        assert!(pwr.backtrace().is_some());
+
+        let err = pwr.try_update(Power::new::<watt>(2.0)).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(&first.to_string()), "{message}");
+        assert!(
+            message.contains(&format!("{}:{}", file!(), line!() - 4)),
+            "{message}"
+        );
+
+        pwr.reset();
+        assert!(pwr.updated_at().is_none());
+        assert!(pwr.backtrace().is_none());
+    }
+
+    #[test]
+    fn test_that_strict_reads_record_read_before_update() {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_strict_reads();
+
+        assert!(pwr.get().is_none());
+        let line = line!() - 1;
+        pwr.update(Power::new::<watt>(1.0));
+
+        let violations = pwr.read_violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].step, 0);
+        assert_eq!(violations[0].location.line(), line);
+        match pwr.try_check() {
+            Err(TrackedStateError::ReadBeforeUpdate { name, step, at }) => {
+                assert_eq!((name.as_str(), step), ("pwr", 0));
+                assert_eq!(at.line(), line);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        pwr.reset();
+        pwr.update(Power::new::<watt>(1.0));
+        assert!(pwr.get().is_some());
+        pwr.check();
+        assert_eq!(pwr.read_violations().len(), 1);
+    }
+
+    #[test]
+    fn test_that_reset_keeps_previous_value() {
+        let mut soc = TrackedState::<f64>::new("soc").with_strict_reads();
+        assert_eq!(soc.get_prev(), None);
+
+        soc.update(0.9);
+        soc.reset();
+        assert_eq!(soc.get_prev(), Some(&0.9));
+
+        soc.update(soc.get_prev().unwrap() - 0.1);
+        soc.check();
+        soc.reset();
+        assert_eq!(soc.get_prev(), Some(&0.8));
+
+        soc.reset();
+        assert_eq!(soc.get_prev(), None);
+        assert!(soc.read_violations().is_empty());
+    }
+
+    #[test]
+    fn test_that_values_without_clone_can_be_reset() {
+        #[derive(Debug, PartialEq)]
+        struct Cell(f64);
+
+        let mut cell = TrackedState::<Cell>::new("cell");
+        cell.update(Cell(3.6));
+        cell.reset();
+        assert_eq!(cell.get_prev(), Some(&Cell(3.6)));
+
+        let mut recorded = TrackedState::<Cell>::new("recorded");
+        recorded.update(Cell(3.6));
+        recorded.reset();
+        recorded.enable_history();
+        assert_eq!(recorded.get_prev(), Some(&Cell(3.6)));
+        recorded.update(Cell(3.5));
+        recorded.reset();
+        assert_eq!(recorded.get_prev(), Some(&Cell(3.5)));
+        recorded.reset();
+        assert_eq!(recorded.get_prev(), None);
+        assert_eq!(
+            recorded.history().unwrap().as_slice(),
+            [None, Some(Cell(3.5)), None]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_that_serde_round_trips_status_and_history() {
+        let mut pwr = TrackedState::<Power>::new("pwr").with_history();
+        pwr.update(Power::new::<watt>(1.0));
+        pwr.reset();
+        pwr.update(Power::new::<watt>(2.0));
+
+        let json = serde_json::to_string(&pwr).unwrap();
+        let mut restored: TrackedState<Power> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.name(), "pwr");
+        assert_eq!(restored.step(), 1);
+        assert_eq!(restored.get(), Some(&Power::new::<watt>(2.0)));
+        assert_eq!(restored.get_prev(), Some(&Power::new::<watt>(1.0)));
+        assert!(restored.try_update(Power::new::<watt>(3.0)).is_err());
+        restored.reset();
+        assert_eq!(restored.history().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_that_double_update_policies_apply() {
+        let mut errored =
+            TrackedState::<f64>::new("errored").with_policy(DoubleUpdatePolicy::Error);
+        errored.update(1.0);
+        errored.update(2.0);
+        assert_eq!(errored.get(), Some(&1.0));
+        assert_eq!(errored.errors().len(), 1);
+        assert!(matches!(
+            errored.try_check(),
+            Err(TrackedStateError::DoubleUpdate { .. })
+        ));
+        errored.reset();
+        errored.update(3.0);
+        errored.check();
+
+        let mut overwritten =
+            TrackedState::<f64>::new("overwritten").with_policy(DoubleUpdatePolicy::Overwrite);
+        overwritten.update(1.0);
+        overwritten.update(2.0);
+        assert_eq!(overwritten.get(), Some(&2.0));
+        overwritten.check();
+
+        let mut ignored =
+            TrackedState::<f64>::new("ignored").with_policy(DoubleUpdatePolicy::IgnoreIfEqual);
+        ignored.update(1.0);
+        ignored.update(1.0);
+        assert!(ignored.try_update(2.0).is_err());
+        assert_eq!(ignored.get(), Some(&1.0));
+        ignored.reset();
+        ignored.update(f64::NAN);
+        ignored.update(f64::NAN);
+        ignored.reset();
+        ignored.update(0.0);
+        assert!(ignored.try_update(-0.0).is_err());
+    }
+
+    #[test]
+    fn test_that_any_debug_value_can_be_tracked() {
+        #[derive(Clone, Debug)]
+        enum Gear {
+            Low,
+            High,
+        }
+
+        let mut gear =
+            TrackedState::<Gear>::new("gear").with_policy(DoubleUpdatePolicy::IgnoreIfEqual);
+        gear.update(Gear::Low);
+        gear.update(Gear::Low);
+        assert!(gear.try_update(Gear::High).is_err());
+        gear.check();
+        gear.reset();
+        assert!(matches!(gear.get_prev(), Some(Gear::Low)));
+
+        let mut label = TrackedState::<String>::new("label");
+        label.update("eco".to_string());
+        assert_eq!(label.get().map(String::as_str), Some("eco"));
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt;
+use std::ops::Add;
+
+use crate::{
+    AnyTracked, CheckFailure, CheckReport, Snapshot, SnapshotError, TrackedAccumulator,
+    TrackedState, path,
+};
+
+/// A collection of [`TrackedState`]s that can be checked and reset together.
+///
+/// Usually derived with `#[derive(TrackedStates)]`, which includes every
+/// `TrackedState` and `TrackedAccumulator` field and recurses into fields
+/// marked `#[tracked_states(nested)]`.
+///
+/// Each state is addressed by its path, which is its name, the same one used
+/// in diagnostics and dependency graphs.  A state without a name is
+/// addressed by the dotted path of field names leading to it, e.g.
+/// `motor.pwr`, which [`name_states`](Self::name_states) turns into its name.
+/// See [`path`](crate::path) for the glob patterns accepted by
+/// [`select`](Self::select).
+pub trait TrackedStates {
+    /// Resets every state in the collection.
+    fn reset_all(&mut self);
+
+    /// Adds a failure to `report` for every state that fails its check, with
+    /// the paths of states without a name prefixed by `prefix`.
+    fn collect_failures(&self, prefix: &str, report: &mut CheckReport);
+
+    /// Calls `f` with the path and the [`AnyTracked`] view of every
+    /// individual state, with the paths of states without a name prefixed by
+    /// `prefix`.
+    fn for_each<'a>(&'a self, prefix: &str, f: &mut dyn FnMut(&str, &'a dyn AnyTracked));
+
+    /// Mutable version of [`for_each`](Self::for_each).
+    fn for_each_mut<'a>(
+        &'a mut self,
+        prefix: &str,
+        f: &mut dyn FnMut(&str, &'a mut dyn AnyTracked),
+    );
+
+    /// Names every state after the dotted path of field names leading to it,
+    /// prefixed with `prefix`, e.g. `vehicle.motor.pwr`.  From then on the
+    /// name is the path of the state.  A single state is renamed to `prefix`
+    /// unless it is empty.
+    fn name_states(&mut self, prefix: &str);
+
+    /// Checks every state, collecting all failures.
+    fn check_report(&self) -> CheckReport {
+        let mut report = CheckReport::new();
+        self.collect_failures("", &mut report);
+        report
+    }
+
+    /// Dotted paths of every state that fails its check.
+    fn unchecked_names(&self) -> Vec<String> {
+        self.check_report()
+            .failures
+            .into_iter()
+            .map(|failure| failure.path)
+            .collect()
+    }
+
+    /// Panics with a report of every state that fails its check.
+    fn check_all(&self) {
+        let report = self.check_report();
+        assert!(report.is_ok(), "{report}");
+    }
+
+    /// Dotted paths of every state.
+    fn paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        self.for_each("", &mut |path, _| paths.push(path.to_string()));
+        paths
+    }
+
+    /// The state at `path`.
+    fn find(&self, path: &str) -> Option<&dyn AnyTracked> {
+        let mut found = None;
+        self.for_each("", &mut |p, state| {
+            if found.is_none() && p == path {
+                found = Some(state);
+            }
+        });
+        found
+    }
+
+    /// Mutable version of [`find`](Self::find).
+    fn find_mut(&mut self, path: &str) -> Option<&mut dyn AnyTracked> {
+        let mut found = None;
+        self.for_each_mut("", &mut |p, state| {
+            if found.is_none() && p == path {
+                found = Some(state);
+            }
+        });
+        found
+    }
+
+    /// Every state whose path matches the glob `pattern`, with its path.
+    fn select(&self, pattern: &str) -> Vec<(String, &dyn AnyTracked)> {
+        let mut selected = Vec::new();
+        self.for_each("", &mut |path, state| {
+            if path::matches(pattern, path) {
+                selected.push((path.to_string(), state));
+            }
+        });
+        selected
+    }
+
+    /// Mutable version of [`select`](Self::select).
+    fn select_mut(&mut self, pattern: &str) -> Vec<(String, &mut dyn AnyTracked)> {
+        let mut selected = Vec::new();
+        self.for_each_mut("", &mut |path, state| {
+            if path::matches(pattern, path) {
+                selected.push((path.to_string(), state));
+            }
+        });
+        selected
+    }
+
+    /// Captures every state, by path.  Fails if two states have the same
+    /// path.
+    fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+        Snapshot::capture(|f| self.for_each("", f))
+    }
+
+    /// Restores every state from the snapshot entry with its path.  Fails
+    /// without changes unless paths and types match exactly and are unique.
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let mut states = Vec::new();
+        self.for_each_mut("", &mut |path, state| {
+            states.push((path.to_string(), state))
+        });
+        snapshot.restore_into(states)
+    }
+
+    /// Turns history recording on or off for every state matching `pattern`.
+    fn set_recording_matching(&mut self, pattern: &str, enabled: bool) {
+        for (_, state) in self.select_mut(pattern) {
+            state.set_recording(enabled);
+        }
+    }
+}
+
+impl<T> TrackedStates for TrackedState<T>
+where
+    T: fmt::Debug + Clone + 'static,
+{
+    fn reset_all(&mut self) {
+        self.reset();
+    }
+
+    fn collect_failures(&self, prefix: &str, report: &mut CheckReport) {
+        if let Err(error) = self.try_check() {
+            let last_value = self.last_known().map(|v| format!("{v:?}"));
+            report.push(CheckFailure::new(
+                leaf_path(prefix, self.name()),
+                last_value,
+                error,
+            ));
+        }
+    }
+
+    fn for_each<'a>(&'a self, prefix: &str, f: &mut dyn FnMut(&str, &'a dyn AnyTracked)) {
+        f(&leaf_path(prefix, self.name()), self);
+    }
+
+    fn for_each_mut<'a>(
+        &'a mut self,
+        prefix: &str,
+        f: &mut dyn FnMut(&str, &'a mut dyn AnyTracked),
+    ) {
+        let path = leaf_path(prefix, self.name());
+        f(&path, self);
+    }
+
+    fn name_states(&mut self, prefix: &str) {
+        if !prefix.is_empty() {
+            self.set_name(prefix);
+        }
+    }
+}
+
+impl<T> TrackedStates for TrackedAccumulator<T>
+where
+    T: fmt::Debug + Clone + Add<Output = T> + 'static,
+{
+    fn reset_all(&mut self) {
+        self.reset();
+    }
+
+    fn collect_failures(&self, prefix: &str, report: &mut CheckReport) {
+        if let Err(error) = self.try_check() {
+            let last_value = Some(format!("{:?}", self.total()));
+            report.push(CheckFailure::new(
+                leaf_path(prefix, self.name()),
+                last_value,
+                error,
+            ));
+        }
+    }
+
+    fn for_each<'a>(&'a self, prefix: &str, f: &mut dyn FnMut(&str, &'a dyn AnyTracked)) {
+        f(&leaf_path(prefix, self.name()), self);
+    }
+
+    fn for_each_mut<'a>(
+        &'a mut self,
+        prefix: &str,
+        f: &mut dyn FnMut(&str, &'a mut dyn AnyTracked),
+    ) {
+        let path = leaf_path(prefix, self.name());
+        f(&path, self);
+    }
+
+    fn name_states(&mut self, prefix: &str) {
+        if !prefix.is_empty() {
+            self.set_name(prefix);
+        }
+    }
+}
+
+/// Path of a state: its name, or the prefix given by its owner if it has
+/// none.
+fn leaf_path(prefix: &str, name: &str) -> String {
+    if name.is_empty() {
+        prefix.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Joins a path prefix and a field name with a dot.
+#[doc(hidden)]
+pub fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(mutation_tracing_untracked))]
+mod tests {
+    use crate::{TrackedAccumulator, TrackedState, TrackedStates};
+
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+    use uom::si::time::second;
+
+    #[derive(Default, TrackedStates)]
+    struct Motor {
+        pwr: TrackedState<Power>,
+        #[tracked_states(skip)]
+        pwr_max: TrackedState<Power>,
+    }
+
+    #[derive(Default, TrackedStates)]
+    struct Vehicle {
+        #[tracked_states(nested)]
+        motor: Motor,
+        dt: TrackedState<Time>,
+        energy: TrackedState<Energy>,
+        total_energy: TrackedAccumulator<Energy>,
+    }
+
+    #[test]
+    fn test_that_derive_lists_unchecked_states() {
+        let mut vehicle = Vehicle::default();
+        vehicle.dt.update(Time::new::<second>(1.0));
+
+        assert_eq!(
+            vehicle.unchecked_names(),
+            vec!["motor.pwr", "energy", "total_energy"]
+        );
+
+        vehicle.motor.pwr.update(Power::new::<watt>(1.0));
+        vehicle
+            .energy
+            .update(*vehicle.motor.pwr.get().unwrap() * *vehicle.dt.get().unwrap());
+        vehicle.total_energy.add(*vehicle.energy.get().unwrap());
+        vehicle.check_all();
+
+        vehicle.reset_all();
+        assert_eq!(vehicle.unchecked_names().len(), 4);
+        assert_eq!(vehicle.motor.pwr_max.step(), 0);
+    }
+
+    #[test]
+    fn test_that_check_report_lists_every_failure() {
+        let mut vehicle = Vehicle::default();
+        vehicle.motor.pwr.update(Power::new::<watt>(1.0));
+        vehicle.reset_all();
+        vehicle.dt.update(Time::new::<second>(1.0));
+
+        let report = vehicle.check_report();
+        assert_eq!(report.paths(), ["motor.pwr", "energy", "total_energy"]);
+        let pwr = &report.failures[0];
+        assert_eq!(pwr.component, "motor");
+        assert_eq!(pwr.step, 1);
+        assert_eq!(
+            pwr.last_value,
+            Some(format!("{:?}", Power::new::<watt>(1.0)))
+        );
+        assert_eq!(report.failures[1].component, "");
+        assert_eq!(report.failures[1].last_value, None);
+        assert!(
+            report
+                .to_string()
+                .starts_with("3 state check(s) failed:\n  motor.pwr in `motor` at step 1")
+        );
+    }
+
+    #[test]
+    fn test_that_states_of_any_debug_value_are_collected() {
+        #[derive(Clone, Debug)]
+        enum Gear {
+            Low,
+            High,
+        }
+
+        #[derive(Default, TrackedStates)]
+        struct Gearbox {
+            gear: TrackedState<Gear>,
+            pwr: TrackedState<Power>,
+        }
+
+        let mut gearbox = Gearbox::default();
+        gearbox.name_states("gearbox");
+        gearbox.gear.update(Gear::Low);
+        gearbox.reset_all();
+        gearbox.gear.update(Gear::High);
+
+        let gear = gearbox.find("gearbox.gear").unwrap();
+        assert_eq!(gear.debug_value().as_deref(), Some("High"));
+        assert_eq!(gear.prev_debug_value().as_deref(), Some("Low"));
+        assert_eq!((gear.si_value(), gear.unit()), (None, None));
+        assert_eq!(gearbox.unchecked_names(), ["gearbox.pwr"]);
+        assert_eq!(gearbox.snapshot().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "4 state check(s) failed")]
+    fn test_that_check_all_reports_every_missing_state() {
+        Vehicle::default().check_all();
+    }
+
+    #[test]
+    fn test_that_states_are_addressed_by_path_and_glob() {
+        let mut vehicle = Vehicle::default();
+        assert_eq!(
+            vehicle.paths(),
+            ["motor.pwr", "dt", "energy", "total_energy"]
+        );
+
+        vehicle.name_states("vehicle");
+        assert_eq!(
+            vehicle.paths(),
+            [
+                "vehicle.motor.pwr",
+                "vehicle.dt",
+                "vehicle.energy",
+                "vehicle.total_energy"
+            ]
+        );
+        assert_eq!(vehicle.motor.pwr.name(), "vehicle.motor.pwr");
+        assert_eq!(
+            vehicle.find("vehicle.dt").unwrap().unit().as_deref(),
+            Some("s")
+        );
+        assert!(vehicle.find("motor.pwr").is_none());
+        assert!(vehicle.find("vehicle.motor.pwr_max").is_none());
+
+        let selected: Vec<_> = vehicle
+            .select("vehicle.*.pwr")
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(selected, ["vehicle.motor.pwr"]);
+
+        vehicle.set_recording_matching("**.pwr", true);
+        vehicle.motor.pwr.update(Power::new::<watt>(2.0));
+        vehicle.reset_all();
+        assert_eq!(
+            vehicle.find("vehicle.motor.pwr").unwrap().si_history(),
+            Some([Some(2.0)].into_iter().collect())
+        );
+        assert!(vehicle.dt.history().is_none());
+
+        vehicle.motor.pwr.update(Power::new::<watt>(2.0));
+        assert_eq!(
+            vehicle.check_report().select("vehicle.motor.*").paths(),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            vehicle.check_report().select("vehicle.*").paths(),
+            ["vehicle.dt", "vehicle.energy", "vehicle.total_energy"]
+        );
+        assert!(
+            vehicle
+                .snapshot()
+                .unwrap()
+                .get("vehicle.motor.pwr")
+                .is_some()
+        );
+
+        vehicle
+            .find_mut("vehicle.motor.pwr")
+            .unwrap()
+            .set_recording(false);
+        assert!(vehicle.motor.pwr.history().is_none());
+    }
+}
@@ -0,0 +1,343 @@
+//! [`TrackedState`] without tracking, compiled instead of
+//! [`tracked_state`](crate::tracked_state) under
+//! `--cfg mutation_tracing_untracked`.
+//!
+//! The API is the same, but the state only holds its name, unit, step and
+//! the current and previous value: updates are never rejected, checks always
+//! pass, and no call sites, backtraces, histories, read violations or
+//! dependencies are recorded.  Updates, reads and resets are inlined stores
+//! and loads of the values plus a step increment, so model code runs
+//! unchanged without any of the per-access bookkeeping.  The state is not a
+//! transparent wrapper, though: the name and unit are kept for diagnostics
+//! and addressing, which makes it larger than two `Option<T>`s, and creating
+//! it allocates the name.
+
+use std::backtrace::Backtrace;
+use std::fmt;
+use std::panic::Location;
+
+use crate::{
+    DoubleUpdatePolicy, History, Iteration, IterationStats, TrackedStateError, policy, value,
+};
+
+/// A state variable, stripped of all bookkeeping.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedState<T: fmt::Debug> {
+    value: Option<T>,
+    prev: Option<T>,
+    name: String,
+    unit: Option<String>,
+    step: usize,
+}
+
+/// The values of a [`TrackedState`], see [`TrackedState::save`].
+#[derive(Clone, Debug)]
+pub(crate) struct SavedState<T> {
+    value: Option<T>,
+    prev: Option<T>,
+    step: usize,
+}
+
+/// A read of a [`TrackedState`] before it was updated in the same step.
+/// Never recorded without tracking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadViolation {
+    /// Step in which the read happened.
+    pub step: usize,
+    /// Call site of the read.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for ReadViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read before update in step {} at {}",
+            self.step, self.location
+        )
+    }
+}
+
+impl<T> Default for TrackedState<T>
+where
+    T: fmt::Debug + 'static,
+{
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl<T> TrackedState<T>
+where
+    T: fmt::Debug,
+{
+    /// Creates a state with the given name, labelled with the unit of a
+    /// [registered](crate::value::register) value type.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self
+    where
+        T: 'static,
+    {
+        Self {
+            value: None,
+            prev: None,
+            name: name.into(),
+            unit: value::unit_of::<T>(),
+            step: 0,
+        }
+    }
+
+    /// Does nothing; double updates always overwrite.
+    #[inline]
+    pub fn with_policy(self, _policy: DoubleUpdatePolicy) -> Self {
+        self
+    }
+
+    /// The global override if set, otherwise the default policy.  It has no
+    /// effect without tracking.
+    pub fn policy(&self) -> DoubleUpdatePolicy {
+        policy::global_policy().unwrap_or_default()
+    }
+
+    /// Always empty.
+    #[inline]
+    pub fn errors(&self) -> &[TrackedStateError] {
+        &[]
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub fn with_strict_reads(self) -> Self {
+        self
+    }
+
+    /// Always empty.
+    #[inline]
+    pub fn read_violations(&self) -> Vec<ReadViolation> {
+        Vec::new()
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub fn with_backtraces(self) -> Self {
+        self
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub fn with_history(self) -> Self {
+        self
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub fn enable_history(&mut self) {}
+
+    /// Always `None`.
+    #[inline]
+    pub fn disable_history(&mut self) -> Option<History<T>> {
+        None
+    }
+
+    /// Always `None`.
+    #[inline]
+    pub fn history(&self) -> Option<&History<T>> {
+        None
+    }
+
+    /// Name of the state.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Renames the state.
+    #[inline]
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    /// Sets the unit label of the state.
+    #[inline]
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.set_unit(unit);
+        self
+    }
+
+    /// Sets the unit label of the state.
+    #[inline]
+    pub fn set_unit(&mut self, unit: impl Into<String>) {
+        self.unit = Some(unit.into());
+    }
+
+    /// Unit label of the state, if any.
+    #[inline]
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Number of resets so far.
+    #[inline]
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Always `None`.
+    #[inline]
+    pub fn updated_at(&self) -> Option<&'static Location<'static>> {
+        None
+    }
+
+    /// Always `None`.
+    #[inline]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
+    /// Sets the value for the current step.
+    #[inline]
+    pub fn update(&mut self, value: T) {
+        self.value = Some(value);
+    }
+
+    /// Sets the value for the current step; never fails.
+    #[inline]
+    pub fn try_update(&mut self, value: T) -> Result<(), TrackedStateError> {
+        self.value = Some(value);
+        Ok(())
+    }
+
+    /// Stores `value`.
+    #[inline]
+    pub(crate) fn set(&mut self, value: T, _caller: &'static Location<'static>) {
+        self.value = Some(value);
+    }
+
+    /// Starts an [`Iteration`] scope.
+    pub fn iterate(&mut self) -> Iteration<'_, T> {
+        Iteration::new(self)
+    }
+
+    /// Starts an [`Iteration`] scope; never fails.
+    pub fn try_iterate(&mut self) -> Result<Iteration<'_, T>, TrackedStateError> {
+        Ok(Iteration::new(self))
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub(crate) fn seal_iteration(&mut self, _stats: IterationStats) {}
+
+    /// Always `None`.
+    #[inline]
+    pub fn iteration_stats(&self) -> Option<IterationStats> {
+        None
+    }
+
+    /// Does nothing.
+    #[inline]
+    pub fn check(&self) {}
+
+    /// Always succeeds.
+    #[inline]
+    pub fn try_check(&self) -> Result<(), TrackedStateError> {
+        Ok(())
+    }
+
+    /// Returns the value for the current step, if it has been updated.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Current value, or the previous step's if not updated yet.
+    #[inline]
+    pub(crate) fn last_known(&self) -> Option<&T> {
+        self.value.as_ref().or(self.prev.as_ref())
+    }
+
+    /// Value for the current step.
+    #[inline]
+    pub(crate) fn current(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Returns the value of the previous step, if it was updated.
+    #[inline]
+    pub fn get_prev(&self) -> Option<&T> {
+        self.prev.as_ref()
+    }
+
+    /// Value of the previous step.
+    #[inline]
+    pub(crate) fn prev(&self) -> Option<&T> {
+        self.prev.as_ref()
+    }
+
+    /// Returns the value for the current step, failing if it has not been
+    /// updated yet.
+    #[track_caller]
+    #[inline]
+    pub fn try_get(&self) -> Result<&T, TrackedStateError> {
+        self.value
+            .as_ref()
+            .ok_or_else(|| TrackedStateError::ReadBeforeUpdate {
+                name: self.name.clone(),
+                step: self.step,
+                at: Location::caller(),
+            })
+    }
+
+    /// Moves the value to the previous step's and starts the next step.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.prev = self.value.take();
+        self.step += 1;
+    }
+}
+
+impl<T> TrackedState<T>
+where
+    T: fmt::Debug + Clone,
+{
+    /// Does nothing.
+    #[inline]
+    pub fn commit_step(&mut self) {}
+
+    /// Copies the current and previous value and the step.
+    pub(crate) fn save(&self) -> SavedState<T> {
+        SavedState {
+            value: self.value.clone(),
+            prev: self.prev.clone(),
+            step: self.step,
+        }
+    }
+
+    /// Puts back what [`save`](Self::save) copied.  The name and unit are
+    /// kept.
+    pub(crate) fn load(&mut self, saved: &SavedState<T>) {
+        self.value = saved.value.clone();
+        self.prev = saved.prev.clone();
+        self.step = saved.step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrackedState;
+
+    #[test]
+    fn test_that_untracked_state_only_keeps_values_and_name() {
+        let mut soc = TrackedState::<f64>::new("soc").with_history();
+        soc.check();
+        soc.update(1.0);
+        soc.update(0.5);
+        soc.reset();
+        soc.update(soc.get_prev().unwrap() - 0.25);
+
+        assert_eq!(soc.get(), Some(&0.25));
+        assert_eq!(soc.history(), None);
+        assert_eq!(soc.name(), "soc");
+        assert_eq!(soc.step(), 1);
+    }
+}
@@ -0,0 +1,427 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{LazyLock, PoisonError, RwLock};
+
+use uom::si::marker::{AngleKind, ConstituentConcentrationKind, InformationKind, SolidAngleKind};
+use uom::si::{Dimension, Quantity, SI};
+use uom::typenum::Integer;
+
+/// The optional capability of a value type to report its unit and its
+/// numeric value in SI units.
+///
+/// Implemented for uom quantities in SI units and for the primitive numeric
+/// types, which are [`register`]ed already.  States of registered types are
+/// labelled with their unit when created and report their values in SI
+/// units for snapshots, diffs and exports; states of any other `Debug` type
+/// work the same but have neither.  Other types can implement it, with the
+/// default methods reporting no unit and no numeric value, and then be
+/// registered.
+pub trait TrackedValue: 'static {
+    /// Symbol of the SI unit the value is expressed in, empty for
+    /// dimensionless quantities and `None` for values without units.
+    fn unit() -> Option<String> {
+        None
+    }
+
+    /// The value as `f64` in SI base units, if it is numeric.
+    fn to_si(&self) -> Option<f64> {
+        None
+    }
+}
+
+impl<D> TrackedValue for Quantity<D, SI<f64>, f64>
+where
+    D: Dimension + ?Sized + 'static,
+{
+    fn unit() -> Option<String> {
+        Some(si_unit::<D>())
+    }
+
+    fn to_si(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl<D> TrackedValue for Quantity<D, SI<f32>, f32>
+where
+    D: Dimension + ?Sized + 'static,
+{
+    fn unit() -> Option<String> {
+        Some(si_unit::<D>())
+    }
+
+    fn to_si(&self) -> Option<f64> {
+        Some(self.value.into())
+    }
+}
+
+macro_rules! impl_tracked_value_for_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl TrackedValue for $ty {
+                fn to_si(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_tracked_value_for_primitive!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl TrackedValue for bool {
+    fn to_si(&self) -> Option<f64> {
+        Some(if *self { 1.0 } else { 0.0 })
+    }
+}
+
+/// Unit and SI conversion of a registered type, erased to work on any
+/// value.
+#[derive(Clone, Copy)]
+struct Capability {
+    unit: fn() -> Option<String>,
+    to_si: fn(&dyn Any) -> Option<f64>,
+}
+
+impl Capability {
+    fn of<T: TrackedValue>() -> Self {
+        Self {
+            unit: T::unit,
+            to_si: |value| value.downcast_ref::<T>().and_then(T::to_si),
+        }
+    }
+}
+
+/// Registered types by [`TypeId`], starting with the built-in ones.
+static REGISTRY: LazyLock<RwLock<HashMap<TypeId, Capability>>> = LazyLock::new(|| {
+    let mut registry = HashMap::new();
+    macro_rules! insert {
+        ($($ty:ty),*) => {
+            $(registry.insert(TypeId::of::<$ty>(), Capability::of::<$ty>());)*
+        };
+    }
+    macro_rules! insert_quantities {
+        ($($quantity:ident),* $(,)?) => {
+            $(insert!(uom::si::f64::$quantity, uom::si::f32::$quantity);)*
+        };
+    }
+    insert!(
+        f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, bool
+    );
+    insert_quantities!(
+        Absement,
+        Acceleration,
+        Action,
+        AmountOfSubstance,
+        Angle,
+        AngularAbsement,
+        AngularAcceleration,
+        AngularJerk,
+        AngularVelocity,
+        Area,
+        ArealDensityOfStates,
+        ArealHeatCapacity,
+        ArealMassDensity,
+        ArealNumberDensity,
+        ArealNumberRate,
+        AvailableEnergy,
+        Capacitance,
+        CatalyticActivity,
+        CatalyticActivityConcentration,
+        Curvature,
+        DiffusionCoefficient,
+        DynamicViscosity,
+        ElectricCharge,
+        ElectricChargeArealDensity,
+        ElectricChargeLinearDensity,
+        ElectricChargeVolumetricDensity,
+        ElectricCurrent,
+        ElectricCurrentDensity,
+        ElectricDipoleMoment,
+        ElectricDisplacementField,
+        ElectricField,
+        ElectricFlux,
+        ElectricPermittivity,
+        ElectricPotential,
+        ElectricQuadrupoleMoment,
+        ElectricalConductance,
+        ElectricalConductivity,
+        ElectricalMobility,
+        ElectricalResistance,
+        ElectricalResistivity,
+        Energy,
+        Force,
+        Frequency,
+        FrequencyDrift,
+        HeatCapacity,
+        HeatFluxDensity,
+        HeatTransfer,
+        Inductance,
+        Information,
+        InformationRate,
+        InverseVelocity,
+        Jerk,
+        Length,
+        LinearDensityOfStates,
+        LinearMassDensity,
+        LinearNumberDensity,
+        LinearNumberRate,
+        LinearPowerDensity,
+        Luminance,
+        LuminousIntensity,
+        MagneticFieldStrength,
+        MagneticFlux,
+        MagneticFluxDensity,
+        MagneticMoment,
+        MagneticPermeability,
+        Mass,
+        MassConcentration,
+        MassDensity,
+        MassFlux,
+        MassPerEnergy,
+        MassRate,
+        Molality,
+        MolarConcentration,
+        MolarEnergy,
+        MolarFlux,
+        MolarHeatCapacity,
+        MolarMass,
+        MolarRadioactivity,
+        MolarVolume,
+        MomentOfInertia,
+        Momentum,
+        Power,
+        PowerRate,
+        Pressure,
+        RadiantExposure,
+        Radioactivity,
+        Ratio,
+        ReciprocalLength,
+        SolidAngle,
+        SpecificArea,
+        SpecificHeatCapacity,
+        SpecificPower,
+        SpecificRadioactivity,
+        SpecificVolume,
+        SurfaceElectricCurrentDensity,
+        TemperatureCoefficient,
+        TemperatureGradient,
+        TemperatureInterval,
+        ThermalConductance,
+        ThermalConductivity,
+        ThermalResistance,
+        ThermodynamicTemperature,
+        Time,
+        Torque,
+        Velocity,
+        Volume,
+        VolumeRate,
+        VolumetricDensityOfStates,
+        VolumetricHeatCapacity,
+        VolumetricNumberDensity,
+        VolumetricNumberRate,
+        VolumetricPowerDensity,
+    );
+    RwLock::new(registry)
+});
+
+/// Registers `T`, so that states created afterwards are labelled with
+/// [`TrackedValue::unit`] and report [`TrackedValue::to_si`].
+///
+/// Needed for quantities of custom uom systems and for other types
+/// implementing [`TrackedValue`]; registering a type again replaces it.
+pub fn register<T: TrackedValue>() {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(TypeId::of::<T>(), Capability::of::<T>());
+}
+
+fn capability<T: 'static>() -> Option<Capability> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&TypeId::of::<T>())
+        .copied()
+}
+
+/// Unit of `T` if it is registered, see [`TrackedValue::unit`].
+pub fn unit_of<T: 'static>() -> Option<String> {
+    capability::<T>().and_then(|capability| (capability.unit)())
+}
+
+/// Converter of `T` values to SI base units, if `T` is registered.  Look it
+/// up once to convert many values, see [`TrackedValue::to_si`].
+pub fn si_converter<T: 'static>() -> Option<impl Fn(&T) -> Option<f64>> {
+    capability::<T>().map(|capability| move |value: &T| (capability.to_si)(value))
+}
+
+/// `value` in SI base units, if its type is registered and it is numeric.
+pub fn to_si<T: 'static>(value: &T) -> Option<f64> {
+    si_converter::<T>().and_then(|convert| convert(value))
+}
+
+/// Symbols of coherent derived SI units, keyed by the exponents of length,
+/// mass, time, current, temperature, amount and luminous intensity.
+const DERIVED_UNITS: &[([i32; 7], &str)] = &[
+    ([1, 0, -1, 0, 0, 0, 0], "m/s"),
+    ([1, 0, -2, 0, 0, 0, 0], "m/s^2"),
+    ([0, 0, -1, 0, 0, 0, 0], "Hz"),
+    ([1, 1, -2, 0, 0, 0, 0], "N"),
+    ([-1, 1, -2, 0, 0, 0, 0], "Pa"),
+    ([2, 1, -2, 0, 0, 0, 0], "J"),
+    ([2, 1, -3, 0, 0, 0, 0], "W"),
+    ([0, 0, 1, 1, 0, 0, 0], "C"),
+    ([2, 1, -3, -1, 0, 0, 0], "V"),
+    ([-2, -1, 4, 2, 0, 0, 0], "F"),
+    ([2, 1, -3, -2, 0, 0, 0], "Ω"),
+    ([-2, -1, 3, 2, 0, 0, 0], "S"),
+    ([2, 1, -2, -1, 0, 0, 0], "Wb"),
+    ([0, 1, -2, -1, 0, 0, 0], "T"),
+    ([2, 1, -2, -2, 0, 0, 0], "H"),
+    ([2, 1, -2, 0, -1, 0, 0], "J/K"),
+];
+
+/// Units of quantities of angle kind, such as torque, which shares its
+/// dimension with energy.
+const ANGLE_UNITS: &[([i32; 7], &str)] = &[
+    ([0, 0, 0, 0, 0, 0, 0], "rad"),
+    ([0, 0, 1, 0, 0, 0, 0], "rad·s"),
+    ([0, 0, -1, 0, 0, 0, 0], "rad/s"),
+    ([0, 0, -2, 0, 0, 0, 0], "rad/s^2"),
+    ([0, 0, -3, 0, 0, 0, 0], "rad/s^3"),
+    ([-1, 0, 0, 0, 0, 0, 0], "rad/m"),
+    ([2, 1, -2, 0, 0, 0, 0], "N·m"),
+];
+
+/// Units of quantities of solid angle kind.
+const SOLID_ANGLE_UNITS: &[([i32; 7], &str)] = &[([0, 0, 0, 0, 0, 0, 0], "sr")];
+
+/// Units of quantities of information kind, stored in bytes by uom.
+const INFORMATION_UNITS: &[([i32; 7], &str)] = &[
+    ([0, 0, 0, 0, 0, 0, 0], "B"),
+    ([0, 0, -1, 0, 0, 0, 0], "B/s"),
+];
+
+/// Units of quantities of constituent concentration kind, such as
+/// radioactivity, which shares its dimension with frequency.
+const CONSTITUENT_CONCENTRATION_UNITS: &[([i32; 7], &str)] = &[([0, 0, -1, 0, 0, 0, 0], "Bq")];
+
+/// Base unit symbols in the order of [`DERIVED_UNITS`] exponents.
+const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// Named units for quantities of kind `K`.  Only the default kind uses the
+/// coherent derived units; for other kinds they would be ambiguous.
+fn named_units<K>() -> &'static [([i32; 7], &'static str)]
+where
+    K: ?Sized + 'static,
+{
+    let kind = TypeId::of::<K>();
+    if kind == TypeId::of::<dyn uom::Kind>() {
+        DERIVED_UNITS
+    } else if kind == TypeId::of::<dyn AngleKind>() {
+        ANGLE_UNITS
+    } else if kind == TypeId::of::<dyn SolidAngleKind>() {
+        SOLID_ANGLE_UNITS
+    } else if kind == TypeId::of::<dyn InformationKind>() {
+        INFORMATION_UNITS
+    } else if kind == TypeId::of::<dyn ConstituentConcentrationKind>() {
+        CONSTITUENT_CONCENTRATION_UNITS
+    } else {
+        &[]
+    }
+}
+
+/// Symbol of the coherent SI unit of dimension `D`, e.g. `W` for power or
+/// `m^2 kg s^-1` where no named unit exists.
+///
+/// The kind of the quantity tells apart quantities of the same dimension,
+/// e.g. torque in `N·m` from energy in `J` and angular velocity in `rad/s`
+/// from frequency in `Hz`.  Quantities of a kind without a named unit for
+/// their dimension fall back to the base unit expression.
+pub fn si_unit<D>() -> String
+where
+    D: Dimension + ?Sized,
+    D::Kind: 'static,
+{
+    let exponents = [
+        D::L::to_i32(),
+        D::M::to_i32(),
+        D::T::to_i32(),
+        D::I::to_i32(),
+        D::Th::to_i32(),
+        D::N::to_i32(),
+        D::J::to_i32(),
+    ];
+    let named = named_units::<D::Kind>();
+    if let Some((_, symbol)) = named.iter().find(|(e, _)| *e == exponents) {
+        return symbol.to_string();
+    }
+    BASE_UNITS
+        .iter()
+        .zip(exponents)
+        .filter(|(_, exponent)| *exponent != 0)
+        .map(|(symbol, exponent)| match exponent {
+            1 => symbol.to_string(),
+            _ => format!("{symbol}^{exponent}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrackedValue, register, to_si, unit_of};
+
+    use uom::si::f64::*;
+    use uom::si::power::kilowatt;
+
+    #[test]
+    fn test_that_quantities_report_si_units_and_values() {
+        assert_eq!(Power::unit().as_deref(), Some("W"));
+        assert_eq!(Energy::unit().as_deref(), Some("J"));
+        assert_eq!(Time::unit().as_deref(), Some("s"));
+        assert_eq!(Mass::unit().as_deref(), Some("kg"));
+        assert_eq!(Ratio::unit().as_deref(), Some(""));
+        assert_eq!(Area::unit().as_deref(), Some("m^2"));
+        assert_eq!(Torque::unit().as_deref(), Some("N·m"));
+        assert_eq!(AngularVelocity::unit().as_deref(), Some("rad/s"));
+        assert_eq!(Angle::unit().as_deref(), Some("rad"));
+        assert_eq!(Frequency::unit().as_deref(), Some("Hz"));
+        assert_eq!(Radioactivity::unit().as_deref(), Some("Bq"));
+        assert_eq!(MassConcentration::unit().as_deref(), Some("m^-3 kg"));
+        assert_eq!(ThermodynamicTemperature::unit().as_deref(), Some("K"));
+        assert_eq!(Power::new::<kilowatt>(1.5).to_si(), Some(1500.0));
+
+        assert_eq!(f64::unit(), None);
+        assert_eq!(2_u8.to_si(), Some(2.0));
+        assert_eq!(true.to_si(), Some(1.0));
+    }
+
+    #[test]
+    fn test_that_registered_types_report_units() {
+        struct Charge(f64);
+
+        impl TrackedValue for Charge {
+            fn unit() -> Option<String> {
+                Some("Ah".to_string())
+            }
+
+            fn to_si(&self) -> Option<f64> {
+                Some(self.0 * 3600.0)
+            }
+        }
+
+        assert_eq!(unit_of::<Power>().as_deref(), Some("W"));
+        assert_eq!(unit_of::<uom::si::f32::Power>().as_deref(), Some("W"));
+        assert_eq!(to_si(&Power::new::<kilowatt>(1.5)), Some(1500.0));
+        assert_eq!(to_si(&3_u8), Some(3.0));
+        assert_eq!(unit_of::<String>(), None);
+        assert_eq!(to_si(&"3".to_string()), None);
+
+        assert_eq!(to_si(&Charge(1.0)), None);
+        register::<Charge>();
+        assert_eq!(unit_of::<Charge>().as_deref(), Some("Ah"));
+        assert_eq!(to_si(&Charge(1.0)), Some(3600.0));
+    }
+}
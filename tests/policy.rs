@@ -0,0 +1,36 @@
+//! Tests of process-wide settings, kept in their own binary so they cannot
+//! affect the unit tests running in parallel.
+
+#![cfg(not(mutation_tracing_untracked))]
+
+use std::sync::{Arc, Mutex};
+
+use mutation_tracing::TrackedState;
+use mutation_tracing::policy::{self, DoubleUpdatePolicy};
+
+#[test]
+fn test_that_global_policy_and_warning_sink_apply() {
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&warnings);
+    policy::set_warning_sink(Some(Box::new(move |err| {
+        sink.lock().unwrap().push(err.to_string())
+    })));
+
+    let mut warned = TrackedState::<f64>::new("warned").with_policy(DoubleUpdatePolicy::Warn);
+    warned.update(1.0);
+    warned.update(2.0);
+    assert_eq!(warned.get(), Some(&2.0));
+
+    let mut overridden = TrackedState::<f64>::new("overridden");
+    policy::set_global_policy(Some(DoubleUpdatePolicy::Warn));
+    overridden.update(1.0);
+    overridden.update(3.0);
+    policy::set_global_policy(None);
+    assert!(overridden.try_update(4.0).is_err());
+    policy::set_warning_sink(None);
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("state `warned` was updated twice in step 0"));
+    assert!(warnings[1].starts_with("state `overridden` was updated twice in step 0"));
+}